@@ -17,22 +17,23 @@
 
 enum
 {
+    // exit codes are registered in contracts/common/error-registry, never renumber them
     KABLETOP_SCRIPT_ERROR = 4,
-    KABLETOP_ARGS_FORMAT_ERROR,
-    KABLETOP_ROUND_FORMAT_ERROR,
-    KABLETOP_EXCESSIVE_ROUNDS,
-    KABLETOP_EXCESSIVE_WITNESS_BYTES,
-    KABLETOP_WRONG_USER_ROUND,
-    KABLETOP_WRONG_MODE,
-    KABLETOP_WRONG_ROUND_SIGNATURE,
-    KABLETOP_CHALLENGE_FORMAT_ERROR,
-    KABLETOP_SETTLEMENT_FORMAT_ERROR,
-    KABLETOP_RESULT_FORMAT_ERROR,
-    KABLETOP_WRONG_LUA_CONTEXT_CODE,
-    KABLETOP_WRONG_LUA_CELLDEP_CODE,
-    KABLETOP_WRONG_LUA_OPERATION_CODE,
-    KABLETOP_WRONG_BATTLE_RESULT,
-    KABLETOP_WRONG_SINCE
+    KABLETOP_ARGS_FORMAT_ERROR = 5,
+    KABLETOP_ROUND_FORMAT_ERROR = 6,
+    KABLETOP_EXCESSIVE_ROUNDS = 7,
+    KABLETOP_EXCESSIVE_WITNESS_BYTES = 8,
+    KABLETOP_WRONG_USER_ROUND = 9,
+    KABLETOP_WRONG_MODE = 10,
+    KABLETOP_WRONG_ROUND_SIGNATURE = 11,
+    KABLETOP_CHALLENGE_FORMAT_ERROR = 12,
+    KABLETOP_SETTLEMENT_FORMAT_ERROR = 13,
+    KABLETOP_RESULT_FORMAT_ERROR = 14,
+    KABLETOP_WRONG_LUA_CONTEXT_CODE = 15,
+    KABLETOP_WRONG_LUA_CELLDEP_CODE = 16,
+    KABLETOP_WRONG_LUA_OPERATION_CODE = 17,
+    KABLETOP_WRONG_BATTLE_RESULT = 18,
+    KABLETOP_WRONG_SINCE = 19
 };
 
 typedef enum
@@ -0,0 +1,40 @@
+use core::mem::size_of;
+use alloc::vec::Vec;
+use crate::{error::Error, stream::StreamFetcher, Blake160};
+
+/// nft collection cell data: [nft(blake160)]...
+pub fn decode_nft_collection(data: &[u8]) -> Result<Vec<Blake160>, Error> {
+    if !data.chunks_exact(size_of::<Blake160>()).remainder().is_empty() {
+        return Err(Error::InvalidNFTData);
+    }
+    let mut sf = StreamFetcher::new(data);
+    let mut nft_collection = Vec::with_capacity(data.len() / size_of::<Blake160>());
+    while sf.remaining() > 0 {
+        nft_collection.push(sf.get_blake160()?);
+    }
+    return Ok(nft_collection);
+}
+
+pub fn encode_nft_collection(nfts: &[Blake160]) -> Vec<u8> {
+    nfts.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_roundtrip_nft_collection() {
+        let nfts = vec![[1u8; 20], [2u8; 20], [1u8; 20]];
+        let data = encode_nft_collection(&nfts);
+        assert_eq!(data.len(), 60);
+        assert_eq!(decode_nft_collection(&data), Ok(nfts));
+        assert_eq!(decode_nft_collection(&[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_reject_partial_nft() {
+        assert_eq!(decode_nft_collection(&[1u8; 30]), Err(Error::InvalidNFTData));
+    }
+}
@@ -0,0 +1,412 @@
+use alloc::{vec, vec::Vec};
+use crate::{error::Error, stream::StreamFetcher, Blake160, Blake256};
+
+// sudt_type_hash(blake256) | sudt_price_perpack(u128)
+pub const EXTENSION_SUDT_PRICE: u8 = 1;
+// tier(u8) | interval(u32) | tier_nft_count(u16) | [nft_index(u16)]...
+pub const EXTENSION_PITY_RULE: u8 = 2;
+// no extension data, purchases of the series have to decrease the stock of its sale cell
+pub const EXTENSION_LIMITED_SALE: u8 = 3;
+// start_block(u64) | end_block(u64) | paused(u8)
+pub const EXTENSION_SALE_WINDOW: u8 = 4;
+// max_packs_per_buyer(u32)
+pub const EXTENSION_PURCHASE_LIMIT: u8 = 5;
+// merkle_root(blake256) | presale_ckb_price_perpack(u64)
+pub const EXTENSION_WHITELIST: u8 = 6;
+// recipient_count(u8) | [recipient_lock_hash(blake256) | share(u16, in basis points)]...
+pub const EXTENSION_REVENUE_SPLIT: u8 = 7;
+// operator_pubkey_hash(blake160)
+pub const EXTENSION_OPERATOR: u8 = 8;
+
+pub const REVENUE_SHARE_TOTAL: u16 = 10000;
+
+/// composer's nft issuance regulation:
+/// series_id(u32) | ckb_price_perpack(u64) | nft_count_perpack(u8) | reveal_delay(u64) | nft_code_hash(blake256) | nft_hash_type(u8)
+///     | nft_count(u16) | [nft(blake160) | weight(u32)]...
+/// followed by optional extensions: [extension_type(u8) | extension_size(u16) | extension_data]...
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NftConfig {
+    pub series_id: u32,
+    pub ckb_price_perpack: u64,
+    pub nft_count_perpack: u8,
+    pub reveal_delay: u64,
+    pub nft_code_hash: Blake256,
+    pub nft_hash_type: u8,
+    pub nfts: Vec<(Blake160, u32)>,
+    pub sudt_price: Option<(Blake256, u128)>,
+    pub pity_rule: Option<PityRule>,
+    pub limited_sale: bool,
+    pub sale_window: Option<SaleWindow>,
+    pub max_packs_per_buyer: Option<u32>,
+    pub whitelist: Option<(Blake256, u64)>,
+    pub revenue_split: Option<Vec<(Blake256, u16)>>,
+    pub operator: Option<Blake160>
+}
+
+/// purchases are accepted from `start_block` to `end_block` inclusive, unless paused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaleWindow {
+    pub start_block: u64,
+    pub end_block: u64,
+    pub paused: bool
+}
+
+/// at least one nft of the tier is guaranteed in every `interval` packs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PityRule {
+    pub tier: u8,
+    pub interval: u32,
+    pub nft_indices: Vec<usize>
+}
+
+impl NftConfig {
+    pub fn decode(data: &[u8]) -> Result<NftConfig, Error> {
+        let mut sf = StreamFetcher::new(data);
+        let mut config = NftConfig {
+            series_id: sf.get_u32()?,
+            ckb_price_perpack: sf.get_u64()?,
+            nft_count_perpack: sf.get_u8()?,
+            reveal_delay: sf.get_u64()?,
+            ..Default::default()
+        };
+        // revealing in the purchase block would let the buyer know the result before paying
+        if config.reveal_delay == 0 {
+            return Err(Error::InvalidNFTData);
+        }
+        // revealed nfts must be put into cells of exactly this type script code
+        config.nft_code_hash = sf.get_blake256()?;
+        config.nft_hash_type = sf.get_u8()?;
+        let nft_count = sf.get_u16()? as usize;
+        if nft_count < 1 {
+            return Err(Error::InvalidNFTData);
+        }
+        // weights are relative and can be listed in any order, but none of them can be zero
+        for _ in 0..nft_count {
+            let nft = sf.get_blake160()?;
+            let nft_weight = sf.get_u32()?;
+            if nft_weight == 0 {
+                return Err(Error::InvalidNFTData);
+            }
+            config.nfts.push((nft, nft_weight));
+        }
+        // every extension can appear at most once, and unknown ones are rejected
+        while sf.remaining() > 0 {
+            let extension_type = sf.get_u8()?;
+            let extension_size = sf.get_u16()? as usize;
+            // each extension is read from its own stream, which must be consumed exactly
+            let mut ext = StreamFetcher::new(sf.get_bytes(extension_size)?);
+            config.decode_extension(extension_type, &mut ext)?;
+            ext.expect_end()?;
+        }
+        // presale discount is given in ckb, so it can't go along with sUDT pricing
+        if config.whitelist.is_some() && config.sudt_price.is_some() {
+            return Err(Error::InvalidNFTData);
+        }
+        // revenue is split in ckb only
+        if config.revenue_split.is_some() && config.sudt_price.is_some() {
+            return Err(Error::InvalidNFTData);
+        }
+        return Ok(config);
+    }
+
+    fn decode_extension(&mut self, extension_type: u8, ext: &mut StreamFetcher) -> Result<(), Error> {
+        match extension_type {
+            EXTENSION_SUDT_PRICE => {
+                if self.sudt_price.is_some() {
+                    return Err(Error::InvalidNFTData);
+                }
+                self.sudt_price = Some((ext.get_blake256()?, ext.get_u128()?));
+            },
+            EXTENSION_PITY_RULE => {
+                if self.pity_rule.is_some() {
+                    return Err(Error::InvalidNFTData);
+                }
+                let tier = ext.get_u8()?;
+                let interval = ext.get_u32()?;
+                let tier_nft_count = ext.get_u16()? as usize;
+                if interval == 0 || tier_nft_count == 0 {
+                    return Err(Error::InvalidNFTData);
+                }
+                let mut nft_indices = vec![];
+                for _ in 0..tier_nft_count {
+                    let nft_index = ext.get_u16()? as usize;
+                    if nft_index >= self.nfts.len() || nft_indices.contains(&nft_index) {
+                        return Err(Error::InvalidNFTData);
+                    }
+                    nft_indices.push(nft_index);
+                }
+                self.pity_rule = Some(PityRule { tier, interval, nft_indices });
+            },
+            EXTENSION_LIMITED_SALE => {
+                if self.limited_sale {
+                    return Err(Error::InvalidNFTData);
+                }
+                self.limited_sale = true;
+            },
+            EXTENSION_SALE_WINDOW => {
+                if self.sale_window.is_some() {
+                    return Err(Error::InvalidNFTData);
+                }
+                let start_block = ext.get_u64()?;
+                let end_block = ext.get_u64()?;
+                let paused = match ext.get_u8()? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(Error::InvalidNFTData)
+                };
+                if start_block > end_block {
+                    return Err(Error::InvalidNFTData);
+                }
+                self.sale_window = Some(SaleWindow { start_block, end_block, paused });
+            },
+            EXTENSION_PURCHASE_LIMIT => {
+                if self.max_packs_per_buyer.is_some() {
+                    return Err(Error::InvalidNFTData);
+                }
+                self.max_packs_per_buyer = Some(ext.get_u32()?);
+            },
+            EXTENSION_WHITELIST => {
+                if self.whitelist.is_some() {
+                    return Err(Error::InvalidNFTData);
+                }
+                self.whitelist = Some((ext.get_blake256()?, ext.get_u64()?));
+            },
+            EXTENSION_REVENUE_SPLIT => {
+                if self.revenue_split.is_some() {
+                    return Err(Error::InvalidNFTData);
+                }
+                let recipient_count = ext.get_u8()? as usize;
+                if recipient_count == 0 {
+                    return Err(Error::InvalidNFTData);
+                }
+                // shares must be nonzero and add up to the whole payment, one for each recipient
+                let mut recipients: Vec<(Blake256, u16)> = vec![];
+                let mut share_total = 0u32;
+                for _ in 0..recipient_count {
+                    let recipient_lock_hash = ext.get_blake256()?;
+                    let share = ext.get_u16()?;
+                    if share == 0 || recipients.iter().any(|(value, _)| value[..] == recipient_lock_hash[..]) {
+                        return Err(Error::InvalidNFTData);
+                    }
+                    share_total += share as u32;
+                    recipients.push((recipient_lock_hash, share));
+                }
+                if share_total != REVENUE_SHARE_TOTAL as u32 {
+                    return Err(Error::InvalidNFTData);
+                }
+                self.revenue_split = Some(recipients);
+            },
+            EXTENSION_OPERATOR => {
+                if self.operator.is_some() {
+                    return Err(Error::InvalidNFTData);
+                }
+                self.operator = Some(ext.get_blake160()?);
+            },
+            _ => return Err(Error::InvalidNFTData)
+        }
+        return Ok(());
+    }
+
+    /// extensions are written in the order of their types, which decoding doesn't depend on
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend_from_slice(&self.series_id.to_le_bytes());
+        data.extend_from_slice(&self.ckb_price_perpack.to_le_bytes());
+        data.push(self.nft_count_perpack);
+        data.extend_from_slice(&self.reveal_delay.to_le_bytes());
+        data.extend_from_slice(&self.nft_code_hash);
+        data.push(self.nft_hash_type);
+        data.extend_from_slice(&(self.nfts.len() as u16).to_le_bytes());
+        for (nft, weight) in self.nfts.iter() {
+            data.extend_from_slice(nft);
+            data.extend_from_slice(&weight.to_le_bytes());
+        }
+        if let Some((sudt_type_hash, sudt_price)) = &self.sudt_price {
+            append_extension(&mut data, EXTENSION_SUDT_PRICE, &encode_sudt_price(sudt_type_hash, *sudt_price));
+        }
+        if let Some(pity_rule) = &self.pity_rule {
+            append_extension(&mut data, EXTENSION_PITY_RULE, &pity_rule.encode());
+        }
+        if self.limited_sale {
+            append_extension(&mut data, EXTENSION_LIMITED_SALE, &[]);
+        }
+        if let Some(sale_window) = &self.sale_window {
+            append_extension(&mut data, EXTENSION_SALE_WINDOW, &sale_window.encode());
+        }
+        if let Some(max_packs_per_buyer) = self.max_packs_per_buyer {
+            append_extension(&mut data, EXTENSION_PURCHASE_LIMIT, &max_packs_per_buyer.to_le_bytes());
+        }
+        if let Some((merkle_root, presale_price)) = &self.whitelist {
+            let mut ext = merkle_root.to_vec();
+            ext.extend_from_slice(&presale_price.to_le_bytes());
+            append_extension(&mut data, EXTENSION_WHITELIST, &ext);
+        }
+        if let Some(recipients) = &self.revenue_split {
+            append_extension(&mut data, EXTENSION_REVENUE_SPLIT, &encode_revenue_split(recipients));
+        }
+        if let Some(operator) = &self.operator {
+            append_extension(&mut data, EXTENSION_OPERATOR, operator);
+        }
+        return data;
+    }
+
+    /// the part of the config deciding what the packs reveal, which purchases snapshot so that the composer
+    /// can still edit prices or pause the sale:
+    /// series_id(u32) | nft_count_perpack(u8) | reveal_delay(u64) | nft_code_hash(blake256) | nft_hash_type(u8)
+    ///     | nft_count(u16) | [nft(blake160) | weight(u32)]... | pity rule extension, if any
+    pub fn encode_draw_rules(&self) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend_from_slice(&self.series_id.to_le_bytes());
+        data.push(self.nft_count_perpack);
+        data.extend_from_slice(&self.reveal_delay.to_le_bytes());
+        data.extend_from_slice(&self.nft_code_hash);
+        data.push(self.nft_hash_type);
+        data.extend_from_slice(&(self.nfts.len() as u16).to_le_bytes());
+        for (nft, weight) in self.nfts.iter() {
+            data.extend_from_slice(nft);
+            data.extend_from_slice(&weight.to_le_bytes());
+        }
+        if let Some(pity_rule) = &self.pity_rule {
+            append_extension(&mut data, EXTENSION_PITY_RULE, &pity_rule.encode());
+        }
+        return data;
+    }
+}
+
+impl SaleWindow {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = self.start_block.to_le_bytes().to_vec();
+        data.extend_from_slice(&self.end_block.to_le_bytes());
+        data.push(self.paused as u8);
+        return data;
+    }
+}
+
+impl PityRule {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = vec![self.tier];
+        data.extend_from_slice(&self.interval.to_le_bytes());
+        data.extend_from_slice(&(self.nft_indices.len() as u16).to_le_bytes());
+        for nft_index in self.nft_indices.iter() {
+            data.extend_from_slice(&(*nft_index as u16).to_le_bytes());
+        }
+        return data;
+    }
+}
+
+pub fn encode_sudt_price(sudt_type_hash: &Blake256, sudt_price_perpack: u128) -> Vec<u8> {
+    let mut data = sudt_type_hash.to_vec();
+    data.extend_from_slice(&sudt_price_perpack.to_le_bytes());
+    return data;
+}
+
+pub fn encode_revenue_split(recipients: &[(Blake256, u16)]) -> Vec<u8> {
+    let mut data = vec![recipients.len() as u8];
+    for (recipient_lock_hash, share) in recipients.iter() {
+        data.extend_from_slice(recipient_lock_hash);
+        data.extend_from_slice(&share.to_le_bytes());
+    }
+    return data;
+}
+
+/// extension_type(u8) | extension_size(u16) | extension_data
+pub fn append_extension(data: &mut Vec<u8>, extension_type: u8, extension_data: &[u8]) {
+    data.push(extension_type);
+    data.extend_from_slice(&(extension_data.len() as u16).to_le_bytes());
+    data.extend_from_slice(extension_data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starter_config() -> NftConfig {
+        NftConfig {
+            series_id: 1,
+            ckb_price_perpack: 100,
+            nft_count_perpack: 5,
+            reveal_delay: 10,
+            nft_code_hash: [7u8; 32],
+            nft_hash_type: 1,
+            nfts: vec![([1u8; 20], 10), ([2u8; 20], 90)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_config_with_every_extension() {
+        let mut config = starter_config();
+        config.pity_rule = Some(PityRule { tier: 1, interval: 10, nft_indices: vec![0] });
+        config.limited_sale = true;
+        config.sale_window = Some(SaleWindow { start_block: 5, end_block: 50, paused: true });
+        config.max_packs_per_buyer = Some(20);
+        config.whitelist = Some(([3u8; 32], 80));
+        config.revenue_split = Some(vec![([4u8; 32], 6000), ([5u8; 32], 4000)]);
+        config.operator = Some([6u8; 20]);
+        assert_eq!(NftConfig::decode(&config.encode()), Ok(config));
+
+        let mut config = starter_config();
+        config.sudt_price = Some(([8u8; 32], 1000));
+        assert_eq!(NftConfig::decode(&config.encode()), Ok(config));
+    }
+
+    #[test]
+    fn test_draw_rules_ignore_sale_settings() {
+        let mut config = starter_config();
+        config.pity_rule = Some(PityRule { tier: 1, interval: 10, nft_indices: vec![0] });
+        let draw_rules = config.encode_draw_rules();
+
+        let mut edited = config.clone();
+        edited.ckb_price_perpack = 150;
+        edited.sale_window = Some(SaleWindow { start_block: 5, end_block: 50, paused: true });
+        edited.whitelist = Some(([3u8; 32], 80));
+        edited.operator = Some([6u8; 20]);
+        assert_eq!(edited.encode_draw_rules(), draw_rules);
+
+        edited.nfts[0].1 = 20;
+        assert_ne!(edited.encode_draw_rules(), draw_rules);
+    }
+
+    #[test]
+    fn test_reject_invalid_config() {
+        let mut config = starter_config();
+        config.reveal_delay = 0;
+        assert_eq!(NftConfig::decode(&config.encode()), Err(Error::InvalidNFTData));
+
+        let mut config = starter_config();
+        config.nfts[0].1 = 0;
+        assert_eq!(NftConfig::decode(&config.encode()), Err(Error::InvalidNFTData));
+
+        let mut config = starter_config();
+        config.sudt_price = Some(([8u8; 32], 1000));
+        config.whitelist = Some(([3u8; 32], 80));
+        assert_eq!(NftConfig::decode(&config.encode()), Err(Error::InvalidNFTData));
+
+        let mut config = starter_config();
+        config.revenue_split = Some(vec![([4u8; 32], 6000), ([5u8; 32], 3000)]);
+        assert_eq!(NftConfig::decode(&config.encode()), Err(Error::InvalidNFTData));
+
+        let mut config = starter_config();
+        config.pity_rule = Some(PityRule { tier: 1, interval: 10, nft_indices: vec![2] });
+        assert_eq!(NftConfig::decode(&config.encode()), Err(Error::InvalidNFTData));
+    }
+
+    #[test]
+    fn test_reject_malformed_extensions() {
+        let data = starter_config().encode();
+        assert_eq!(NftConfig::decode(&data[..data.len() - 1]), Err(Error::Encoding));
+
+        let mut duplicated = data.clone();
+        append_extension(&mut duplicated, EXTENSION_LIMITED_SALE, &[]);
+        append_extension(&mut duplicated, EXTENSION_LIMITED_SALE, &[]);
+        assert_eq!(NftConfig::decode(&duplicated), Err(Error::InvalidNFTData));
+
+        let mut oversized = data.clone();
+        append_extension(&mut oversized, EXTENSION_PURCHASE_LIMIT, &[1, 0, 0, 0, 0]);
+        assert_eq!(NftConfig::decode(&oversized), Err(Error::Encoding));
+
+        let mut unknown = data.clone();
+        append_extension(&mut unknown, 0xff, &[]);
+        assert_eq!(NftConfig::decode(&unknown), Err(Error::InvalidNFTData));
+    }
+}
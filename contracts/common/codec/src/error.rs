@@ -0,0 +1,9 @@
+/// Error of decoding cell data, which contracts map into their own error codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    Encoding,
+    InvalidNFTData,
+    InvalidPaymentData,
+    InvalidSaleData,
+    InvalidSealedPackData
+}
@@ -0,0 +1,138 @@
+
+//! Molecule layouts of the kabletop channel, whose lock script is written in C and reads them with the
+//! generated C reader, so only the std clients build them in Rust.
+
+// generated by moleculec, so it is left as it comes out
+#[allow(clippy::all)]
+mod kabletop;
+use alloc::vec::Vec;
+use molecule::prelude::{Byte, Builder, Entity};
+pub use kabletop::*;
+
+fn uint8_t(v: u8) -> kabletop::Uint8T {
+    kabletop::Uint8TBuilder::default().set([Byte::from(v); 1]).build()
+}
+
+fn uint64_t(v: u64) -> kabletop::Uint64T {
+    let mut mol_bytes: [Byte; 8] = [Byte::default(); 8];
+    let bytes = v.to_le_bytes();
+    for i in 0..8 {
+        mol_bytes[i] = Byte::from(bytes[i]);
+    }
+    kabletop::Uint64TBuilder::default().set(mol_bytes).build()
+}
+
+fn blake160_t(v: [u8; 20]) -> kabletop::Blake160 {
+    let mut mol_bytes: [Byte; 20] = [Byte::default(); 20];
+    for i in 0..20 {
+        mol_bytes[i] = Byte::from(v[i]);
+    }
+    kabletop::Blake160Builder::default().set(mol_bytes).build()
+}
+
+fn blake256_t(v: [u8; 32]) -> kabletop::Blake256 {
+    let mut mol_bytes: [Byte; 32] = [Byte::default(); 32];
+    for i in 0..32 {
+        mol_bytes[i] = Byte::from(v[i]);
+    }
+    kabletop::Blake256Builder::default().set(mol_bytes).build()
+}
+
+fn signature_t(v: [u8; 65]) -> kabletop::Signature {
+	let mut mol_bytes: [Byte; 65] = [Byte::default(); 65];
+	for i in 0..65 {
+        mol_bytes[i] = Byte::from(v[i]);
+	}
+    kabletop::SignatureBuilder::default().set(mol_bytes).build()
+}
+
+fn nfts_t(v: Vec<[u8; 20]>) -> kabletop::Nfts {
+    let blake160s = v
+        .into_iter()
+        .map(blake160_t)
+        .collect::<Vec<kabletop::Blake160>>();
+    kabletop::NftsBuilder::default().set(blake160s).build()
+}
+
+fn hashes_t(v: Vec<[u8; 32]>) -> kabletop::Hashes {
+    let hashes = v
+        .into_iter()
+        .map(&|hash| blake256_t(hash))
+        .collect::<Vec<_>>();
+    kabletop::HashesBuilder::default().set(hashes).build()
+}
+
+fn bytes_t(v: &[u8]) -> kabletop::Bytes {
+    let bytes = v
+        .to_vec()
+        .iter()
+        .map(|byte| Byte::new(*byte))
+        .collect::<Vec<Byte>>();
+    kabletop::Bytes::new_builder()
+        .set(bytes)
+        .build()
+}
+
+pub fn to_vec<T: Entity>(t: &T) -> Vec<u8> {
+    t.as_bytes().to_vec()
+}
+
+/// channel settings both users agree on before staking into the kabletop lock
+pub struct LockArgs {
+    pub user_staking_ckb: u64,
+    pub user_deck_size: u8,
+    pub begin_blocknumber: u64,
+    pub lock_code_hash: [u8; 32],
+    pub user1_pkhash: [u8; 20],
+    pub user1_nfts: Vec<[u8; 20]>,
+    pub user2_pkhash: [u8; 20],
+    pub user2_nfts: Vec<[u8; 20]>
+}
+
+pub fn lock_args(raw: LockArgs, luacode_hashes: Vec<[u8; 32]>) -> Args {
+    Args::new_builder()
+        .user_staking_ckb(uint64_t(raw.user_staking_ckb))
+        .user_deck_size(uint8_t(raw.user_deck_size))
+        .begin_blocknumber(uint64_t(raw.begin_blocknumber))
+        .lock_code_hash(blake256_t(raw.lock_code_hash))
+		.lua_code_hashes(hashes_t(luacode_hashes))
+        .user1_pkhash(blake160_t(raw.user1_pkhash))
+        .user1_nfts(nfts_t(raw.user1_nfts))
+        .user2_pkhash(blake160_t(raw.user2_pkhash))
+        .user2_nfts(nfts_t(raw.user2_nfts))
+        .build()
+}
+
+pub fn round(user_type: u8, operations: Vec<&str>) -> Round {
+    let operations = operations
+        .iter()
+        .map(|bytes| bytes_t(bytes.as_bytes()))
+        .collect::<Vec<kabletop::Bytes>>();
+    let operations = Operations::new_builder()
+        .set(operations)
+        .build();
+    Round::new_builder()
+        .user_type(uint8_t(user_type))
+        .operations(operations)
+        .build()
+}
+
+pub fn challenge(
+	challenger: u8, count: u8, snapshot_position: u8, snapshot_hashproof: [u8; 32], snapshot_signature: [u8; 65], operations: Vec<&str>
+) -> Challenge {
+    let operations = operations
+        .iter()
+        .map(|bytes| bytes_t(bytes.as_bytes()))
+        .collect::<Vec<kabletop::Bytes>>();
+    let operations = Operations::new_builder()
+        .set(operations)
+        .build();
+    Challenge::new_builder()
+		.count(uint8_t(count))
+        .challenger(uint8_t(challenger))
+        .snapshot_position(uint8_t(snapshot_position))
+		.snapshot_hashproof(blake256_t(snapshot_hashproof))
+		.snapshot_signature(signature_t(snapshot_signature))
+		.operations(operations)
+        .build()
+}
@@ -0,0 +1,23 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+// early returns are the style of every contract sharing these layouts
+#![allow(clippy::needless_return)]
+
+//! Data layouts shared by the contracts and their std clients, so that both sides encode and
+//! decode cell data the same way.
+
+extern crate alloc;
+
+pub mod error;
+pub mod stream;
+pub mod config;
+pub mod payment;
+pub mod sale;
+pub mod sealed_pack;
+pub mod collection;
+pub mod kabletop;
+
+pub use error::Error;
+pub use stream::StreamFetcher;
+
+pub type Blake160 = [u8; 20];
+pub type Blake256 = [u8; 32];
@@ -0,0 +1,197 @@
+use core::mem::size_of;
+use alloc::{vec, vec::Vec};
+use crate::{error::Error, stream::StreamFetcher, Blake256};
+
+pub const PAYMENT_DATA_VERSION_LEGACY: u8 = 0;
+pub const PAYMENT_DATA_VERSION_COUNTER: u8 = 1;
+pub const PAYMENT_DATA_VERSION_SERIES: u8 = 2;
+pub const PAYMENT_DATA_VERSION_REFUNDABLE: u8 = 3;
+pub const PAYMENT_DATA_VERSION_PITY: u8 = 4;
+pub const PAYMENT_DATA_VERSION_LIFETIME: u8 = 5;
+pub const PAYMENT_DATA_VERSION_SNAPSHOT: u8 = 6;
+pub const PAYMENT_DATA_VERSION_PARTIAL: u8 = 7;
+pub const PAYMENT_DATA_VERSION_REGISTERED: u8 = 8;
+
+/// pack counter kept in buyer's payment cell, which is either a legacy single byte of series 0
+/// or a versioned layout where every version appends fields to the previous one:
+/// version(u8) | count(u32) | series_id(u32, since version 2) | paid(u64, since version 3)
+///     | packs_since_hit(u32, since version 4) | lifetime_count(u32, since version 5)
+///     | config_hash(blake256, since version 6) | draw_offset(u32, since version 7) | anchor_block(u64, since version 7)
+///     | registered(u8, since version 8)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PaymentData {
+    pub version: u8,
+    pub count: u32,
+    pub series_id: u32,
+    pub paid: u64,
+    pub packs_since_hit: u32,
+    pub lifetime_count: u32,
+    pub config_hash: Blake256,
+    pub draw_offset: u32,
+    pub anchor_block: u64,
+    pub registered: bool
+}
+
+impl PaymentData {
+    pub fn decode(data: &[u8]) -> Result<PaymentData, Error> {
+        if data.len() == size_of::<u8>() {
+            return Ok(PaymentData { version: PAYMENT_DATA_VERSION_LEGACY, count: data[0] as u32, ..Default::default() });
+        }
+        if data.is_empty() {
+            return Err(Error::InvalidPaymentData);
+        }
+        let version = data[0];
+        if !(PAYMENT_DATA_VERSION_COUNTER..=PAYMENT_DATA_VERSION_REGISTERED).contains(&version) {
+            return Err(Error::InvalidPaymentData);
+        }
+        if data.len() != Self::size_of_version(version) {
+            return Err(Error::InvalidPaymentData);
+        }
+        let mut payment = PaymentData { version, ..Default::default() };
+        let mut sf = StreamFetcher { index: size_of::<u8>(), stream: data };
+        payment.count = sf.get_u32()?;
+        if version >= PAYMENT_DATA_VERSION_SERIES {
+            payment.series_id = sf.get_u32()?;
+        }
+        if version >= PAYMENT_DATA_VERSION_REFUNDABLE {
+            payment.paid = sf.get_u64()?;
+        }
+        if version >= PAYMENT_DATA_VERSION_PITY {
+            payment.packs_since_hit = sf.get_u32()?;
+        }
+        if version >= PAYMENT_DATA_VERSION_LIFETIME {
+            payment.lifetime_count = sf.get_u32()?;
+        }
+        if version >= PAYMENT_DATA_VERSION_SNAPSHOT {
+            payment.config_hash = sf.get_blake256()?;
+        }
+        if version >= PAYMENT_DATA_VERSION_PARTIAL {
+            payment.draw_offset = sf.get_u32()?;
+            payment.anchor_block = sf.get_u64()?;
+        }
+        if version >= PAYMENT_DATA_VERSION_REGISTERED {
+            payment.registered = match sf.get_u8()? {
+                0 => false,
+                1 => true,
+                _ => return Err(Error::InvalidPaymentData)
+            };
+        }
+        return Ok(payment);
+    }
+
+    /// fields that the version doesn't carry are left out, so they are expected to be zero
+    pub fn encode(&self) -> Vec<u8> {
+        if self.version == PAYMENT_DATA_VERSION_LEGACY {
+            return vec![self.count as u8];
+        }
+        let mut data = vec![self.version];
+        data.extend_from_slice(&self.count.to_le_bytes());
+        if self.version >= PAYMENT_DATA_VERSION_SERIES {
+            data.extend_from_slice(&self.series_id.to_le_bytes());
+        }
+        if self.version >= PAYMENT_DATA_VERSION_REFUNDABLE {
+            data.extend_from_slice(&self.paid.to_le_bytes());
+        }
+        if self.version >= PAYMENT_DATA_VERSION_PITY {
+            data.extend_from_slice(&self.packs_since_hit.to_le_bytes());
+        }
+        if self.version >= PAYMENT_DATA_VERSION_LIFETIME {
+            data.extend_from_slice(&self.lifetime_count.to_le_bytes());
+        }
+        if self.version >= PAYMENT_DATA_VERSION_SNAPSHOT {
+            data.extend_from_slice(&self.config_hash);
+        }
+        if self.version >= PAYMENT_DATA_VERSION_PARTIAL {
+            data.extend_from_slice(&self.draw_offset.to_le_bytes());
+            data.extend_from_slice(&self.anchor_block.to_le_bytes());
+        }
+        if self.version >= PAYMENT_DATA_VERSION_REGISTERED {
+            data.push(self.registered as u8);
+        }
+        return data;
+    }
+
+    fn size_of_version(version: u8) -> usize {
+        let mut size = size_of::<u8>() + size_of::<u32>();
+        if version >= PAYMENT_DATA_VERSION_SERIES {
+            size += size_of::<u32>();
+        }
+        if version >= PAYMENT_DATA_VERSION_REFUNDABLE {
+            size += size_of::<u64>();
+        }
+        if version >= PAYMENT_DATA_VERSION_PITY {
+            size += size_of::<u32>();
+        }
+        if version >= PAYMENT_DATA_VERSION_LIFETIME {
+            size += size_of::<u32>();
+        }
+        if version >= PAYMENT_DATA_VERSION_SNAPSHOT {
+            size += size_of::<Blake256>();
+        }
+        if version >= PAYMENT_DATA_VERSION_PARTIAL {
+            size += size_of::<u32>() + size_of::<u64>();
+        }
+        if version >= PAYMENT_DATA_VERSION_REGISTERED {
+            size += size_of::<u8>();
+        }
+        return size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_every_version() {
+        for version in PAYMENT_DATA_VERSION_LEGACY..=PAYMENT_DATA_VERSION_REGISTERED {
+            let mut payment = PaymentData { version, count: 3, ..Default::default() };
+            if version >= PAYMENT_DATA_VERSION_SERIES {
+                payment.series_id = 2;
+            }
+            if version >= PAYMENT_DATA_VERSION_REFUNDABLE {
+                payment.paid = 300;
+            }
+            if version >= PAYMENT_DATA_VERSION_PITY {
+                payment.packs_since_hit = 4;
+            }
+            if version >= PAYMENT_DATA_VERSION_LIFETIME {
+                payment.lifetime_count = 7;
+            }
+            if version >= PAYMENT_DATA_VERSION_SNAPSHOT {
+                payment.config_hash = [9u8; 32];
+            }
+            if version >= PAYMENT_DATA_VERSION_PARTIAL {
+                payment.draw_offset = 10;
+                payment.anchor_block = 1000;
+            }
+            if version >= PAYMENT_DATA_VERSION_REGISTERED {
+                payment.registered = true;
+            }
+            let data = payment.encode();
+            assert_eq!(data.len(), if version == 0 { 1 } else { PaymentData::size_of_version(version) });
+            assert_eq!(PaymentData::decode(&data), Ok(payment));
+        }
+    }
+
+    #[test]
+    fn test_reject_invalid_payment_data() {
+        assert_eq!(PaymentData::decode(&[]), Err(Error::InvalidPaymentData));
+        let mut data = PaymentData { version: PAYMENT_DATA_VERSION_REFUNDABLE, ..Default::default() }.encode();
+        data.push(0);
+        assert_eq!(PaymentData::decode(&data), Err(Error::InvalidPaymentData));
+        data[0] = PAYMENT_DATA_VERSION_REGISTERED + 1;
+        assert_eq!(PaymentData::decode(&data), Err(Error::InvalidPaymentData));
+        let mut data = PaymentData { version: PAYMENT_DATA_VERSION_REGISTERED, ..Default::default() }.encode();
+        *data.last_mut().unwrap() = 2;
+        assert_eq!(PaymentData::decode(&data), Err(Error::InvalidPaymentData));
+    }
+
+    #[test]
+    fn test_decode_counter_payment_data() {
+        let data = [PAYMENT_DATA_VERSION_COUNTER, 44, 1, 0, 0];
+        let payment = PaymentData::decode(&data).unwrap();
+        assert_eq!((payment.count, payment.series_id), (300, 0));
+        assert_eq!(payment.encode(), data.to_vec());
+    }
+}
@@ -0,0 +1,65 @@
+use core::mem::size_of;
+use alloc::vec::Vec;
+use crate::{error::Error, stream::StreamFetcher, Blake160};
+
+pub const SALE_ARGS_SIZE: usize = size_of::<Blake160>() + size_of::<u32>();
+
+/// sale cell of a limited-edition series shares composer's ownerlock and is typed by payment script with args:
+/// composer_pubkey_hash(blake160) | series_id(u32)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaleArgs {
+    pub composer_pubkey_hash: Blake160,
+    pub series_id: u32
+}
+
+impl SaleArgs {
+    pub fn decode(data: &[u8]) -> Result<SaleArgs, Error> {
+        if data.len() != SALE_ARGS_SIZE {
+            return Err(Error::InvalidSaleData);
+        }
+        let mut sf = StreamFetcher::new(data);
+        return Ok(SaleArgs {
+            composer_pubkey_hash: sf.get_blake160()?,
+            series_id: sf.get_u32()?
+        });
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = self.composer_pubkey_hash.to_vec();
+        data.extend_from_slice(&self.series_id.to_le_bytes());
+        return data;
+    }
+}
+
+/// sale cell data holds the remaining stock: remaining_packs(u32)
+pub fn decode_sale_stock(data: &[u8]) -> Result<u32, Error> {
+    if data.len() != size_of::<u32>() {
+        return Err(Error::InvalidSaleData);
+    }
+    return StreamFetcher::new(data).get_u32();
+}
+
+pub fn encode_sale_stock(remaining_packs: u32) -> Vec<u8> {
+    remaining_packs.to_le_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_sale_cell() {
+        let args = SaleArgs { composer_pubkey_hash: [1u8; 20], series_id: 3 };
+        let data = args.encode();
+        assert_eq!(data.len(), SALE_ARGS_SIZE);
+        assert_eq!(SaleArgs::decode(&data), Ok(args));
+        assert_eq!(decode_sale_stock(&encode_sale_stock(500)), Ok(500));
+    }
+
+    #[test]
+    fn test_reject_invalid_sale_cell() {
+        assert_eq!(SaleArgs::decode(&[1u8; 20]), Err(Error::InvalidSaleData));
+        assert_eq!(decode_sale_stock(&[]), Err(Error::InvalidSaleData));
+        assert_eq!(decode_sale_stock(&[1u8; 8]), Err(Error::InvalidSaleData));
+    }
+}
@@ -0,0 +1,73 @@
+use core::mem::size_of;
+use alloc::vec::Vec;
+use crate::{error::Error, stream::StreamFetcher, Blake256};
+
+pub const SEALED_PACK_ARGS_SIZE: usize = size_of::<Blake256>();
+pub const SEALED_PACK_DATA_SIZE: usize = size_of::<u32>() + size_of::<u32>() + size_of::<u64>() + size_of::<Blake256>() * 2;
+
+/// unrevealed packs carried outside of buyer's payment cell, typed by payment script with args:
+/// ownerlock_hash(blake256)
+/// and data: series_id(u32) | count(u32) | anchor_block(u64) | pack_id(blake256) | draw_hash(blake256)
+/// where anchor_block is the block the pack was minted in, filled by its first transfer (0 until then),
+/// and draw_hash is the digest of the draw rules of the series at mint
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SealedPack {
+    pub series_id: u32,
+    pub count: u32,
+    pub anchor_block: u64,
+    pub pack_id: Blake256,
+    pub draw_hash: Blake256
+}
+
+impl SealedPack {
+    pub fn decode(data: &[u8]) -> Result<SealedPack, Error> {
+        if data.len() != SEALED_PACK_DATA_SIZE {
+            return Err(Error::InvalidSealedPackData);
+        }
+        let mut sf = StreamFetcher::new(data);
+        let series_id = sf.get_u32()?;
+        let count = sf.get_u32()?;
+        if count == 0 {
+            return Err(Error::InvalidSealedPackData);
+        }
+        return Ok(SealedPack {
+            series_id,
+            count,
+            anchor_block: sf.get_u64()?,
+            pack_id: sf.get_blake256()?,
+            draw_hash: sf.get_blake256()?
+        });
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(SEALED_PACK_DATA_SIZE);
+        data.extend_from_slice(&self.series_id.to_le_bytes());
+        data.extend_from_slice(&self.count.to_le_bytes());
+        data.extend_from_slice(&self.anchor_block.to_le_bytes());
+        data.extend_from_slice(&self.pack_id);
+        data.extend_from_slice(&self.draw_hash);
+        return data;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_sealed_pack() {
+        let sealed_pack = SealedPack { series_id: 1, count: 2, anchor_block: 1000, pack_id: [3u8; 32], draw_hash: [4u8; 32] };
+        let data = sealed_pack.encode();
+        assert_eq!(data.len(), SEALED_PACK_DATA_SIZE);
+        assert_eq!(SealedPack::decode(&data), Ok(sealed_pack));
+    }
+
+    #[test]
+    fn test_reject_invalid_sealed_pack() {
+        let data = SealedPack { count: 0, ..Default::default() }.encode();
+        assert_eq!(SealedPack::decode(&data), Err(Error::InvalidSealedPackData));
+        let data = SealedPack { count: 1, ..Default::default() }.encode();
+        assert_eq!(SealedPack::decode(&data[..data.len() - 1]), Err(Error::InvalidSealedPackData));
+        assert_eq!(SealedPack::decode(&[]), Err(Error::InvalidSealedPackData));
+    }
+}
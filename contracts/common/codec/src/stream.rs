@@ -0,0 +1,131 @@
+use core::{convert::TryInto, mem::size_of};
+use crate::{error::Error, Blake160, Blake256};
+
+// reads little-endian fields one after another, every read fails with `Error::Encoding` instead of
+// panicking once the stream runs short
+pub struct StreamFetcher<'load> {
+    pub index: usize,
+    pub stream: &'load [u8]
+}
+
+impl<'load> StreamFetcher<'load> {
+    pub fn new(stream: &'load [u8]) -> Self {
+        StreamFetcher { index: 0, stream }
+    }
+
+    fn next<T>(&mut self) -> Result<&'load [u8], Error> {
+        self.get_bytes(size_of::<T>())
+    }
+
+    pub fn get_bytes(&mut self, size: usize) -> Result<&'load [u8], Error> {
+        if size > self.remaining() {
+            return Err(Error::Encoding);
+        }
+        let s = self.index;
+        self.index += size;
+        return Ok(&self.stream[s..self.index]);
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.stream.len().saturating_sub(self.index)
+    }
+
+    pub fn expect_end(&self) -> Result<(), Error> {
+        if self.remaining() != 0 {
+            return Err(Error::Encoding);
+        }
+        return Ok(());
+    }
+
+    pub fn get_u128(&mut self) -> Result<u128, Error> {
+        return Ok(u128::from_le_bytes(self.next::<u128>()?.try_into().unwrap()));
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, Error> {
+        return Ok(u64::from_le_bytes(self.next::<u64>()?.try_into().unwrap()));
+    }
+
+    pub fn get_u16(&mut self) -> Result<u16, Error> {
+        return Ok(u16::from_le_bytes(self.next::<u16>()?.try_into().unwrap()));
+    }
+
+    pub fn get_u32(&mut self) -> Result<u32, Error> {
+        return Ok(u32::from_le_bytes(self.next::<u32>()?.try_into().unwrap()));
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, Error> {
+        return Ok(self.next::<u8>()?[0]);
+    }
+
+    pub fn get_blake160(&mut self) -> Result<Blake160, Error> {
+        return Ok(self.next::<Blake160>()?.try_into().unwrap());
+    }
+
+    pub fn get_blake256(&mut self) -> Result<Blake256, Error> {
+        return Ok(self.next::<Blake256>()?.try_into().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_read_fields_in_order() {
+        let mut data = vec![7u8];
+        data.extend_from_slice(&0x1234u16.to_le_bytes());
+        data.extend_from_slice(&0xdeadbeefu32.to_le_bytes());
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        data.extend_from_slice(&[1u8; 20]);
+        let mut sf = StreamFetcher::new(&data);
+        assert_eq!(sf.get_u8().ok(), Some(7));
+        assert_eq!(sf.get_u16().ok(), Some(0x1234));
+        assert_eq!(sf.get_u32().ok(), Some(0xdeadbeef));
+        assert_eq!(sf.get_u64().ok(), Some(u64::MAX));
+        assert_eq!(sf.get_blake160().ok(), Some([1u8; 20]));
+        assert_eq!(sf.remaining(), 0);
+        assert!(sf.expect_end().is_ok());
+    }
+
+    #[test]
+    fn test_fail_on_short_stream() {
+        let data = vec![1u8, 2, 3];
+        let mut sf = StreamFetcher::new(&data);
+        assert!(matches!(sf.get_u32(), Err(Error::Encoding)));
+        // a failed read doesn't consume the stream
+        assert_eq!(sf.remaining(), 3);
+        assert_eq!(sf.get_u16().ok(), Some(0x0201));
+        assert!(matches!(sf.get_u16(), Err(Error::Encoding)));
+        assert!(matches!(sf.get_blake256(), Err(Error::Encoding)));
+        assert!(matches!(sf.get_u128(), Err(Error::Encoding)));
+    }
+
+    #[test]
+    fn test_get_bytes() {
+        let data = vec![1u8, 2, 3, 4, 5];
+        let mut sf = StreamFetcher::new(&data);
+        assert_eq!(sf.get_bytes(0).ok(), Some(&[][..]));
+        assert_eq!(sf.get_bytes(2).ok(), Some(&[1u8, 2][..]));
+        assert!(matches!(sf.get_bytes(4), Err(Error::Encoding)));
+        assert_eq!(sf.get_bytes(3).ok(), Some(&[3u8, 4, 5][..]));
+        assert!(sf.expect_end().is_ok());
+    }
+
+    #[test]
+    fn test_expect_end_with_trailing_bytes() {
+        let data = vec![1u8, 2, 3];
+        let mut sf = StreamFetcher::new(&data);
+        assert!(sf.get_u16().is_ok());
+        assert_eq!(sf.remaining(), 1);
+        assert!(matches!(sf.expect_end(), Err(Error::Encoding)));
+    }
+
+    #[test]
+    fn test_index_past_stream_end() {
+        let data = vec![1u8, 2];
+        let mut sf = StreamFetcher { index: 5, stream: &data };
+        assert_eq!(sf.remaining(), 0);
+        assert!(matches!(sf.get_u8(), Err(Error::Encoding)));
+    }
+}
@@ -0,0 +1,42 @@
+use crate::ErrorInfo;
+
+/// exit codes of the C kabletop contract, including the syscall and secp256k1 lock codes it passes through
+pub const ERRORS: &[ErrorInfo] = &[
+    // ckb syscalls (ckb_consts.h)
+    ErrorInfo::new(1, "CKB_INDEX_OUT_OF_BOUND", "a cell, witness or header was loaded past the end of its source"),
+    ErrorInfo::new(2, "CKB_ITEM_MISSING", "a required field is missing"),
+    ErrorInfo::new(3, "CKB_LENGTH_NOT_ENOUGH", "loaded data is longer than the buffer expected by the contract"),
+
+    // kabletop (core.h)
+    ErrorInfo::new(4, "KABLETOP_SCRIPT_ERROR", "kabletop script is too large or can't be loaded"),
+    ErrorInfo::new(5, "KABLETOP_ARGS_FORMAT_ERROR", "kabletop lock args are malformed"),
+    ErrorInfo::new(6, "KABLETOP_ROUND_FORMAT_ERROR", "a round in the witnesses is malformed"),
+    ErrorInfo::new(7, "KABLETOP_EXCESSIVE_ROUNDS", "more rounds are submitted than the channel allows"),
+    ErrorInfo::new(8, "KABLETOP_EXCESSIVE_WITNESS_BYTES", "a round witness exceeds the size limit"),
+    ErrorInfo::new(9, "KABLETOP_WRONG_USER_ROUND", "rounds don't alternate between the two players"),
+    ErrorInfo::new(10, "KABLETOP_WRONG_MODE", "transaction matches neither settlement nor challenge mode"),
+    ErrorInfo::new(11, "KABLETOP_WRONG_ROUND_SIGNATURE", "a round isn't signed by the opponent"),
+    ErrorInfo::new(12, "KABLETOP_CHALLENGE_FORMAT_ERROR", "challenge cell data is malformed or inconsistent"),
+    ErrorInfo::new(13, "KABLETOP_SETTLEMENT_FORMAT_ERROR", "settlement outputs don't pay back the players correctly"),
+    ErrorInfo::new(14, "KABLETOP_RESULT_FORMAT_ERROR", "battle result returned by the lua code is malformed"),
+    ErrorInfo::new(15, "KABLETOP_WRONG_LUA_CONTEXT_CODE", "lua context code fails to run"),
+    ErrorInfo::new(16, "KABLETOP_WRONG_LUA_CELLDEP_CODE", "lua code in cell deps fails to run or mismatches its hash"),
+    ErrorInfo::new(17, "KABLETOP_WRONG_LUA_OPERATION_CODE", "lua operation of a round fails to run"),
+    ErrorInfo::new(18, "KABLETOP_WRONG_BATTLE_RESULT", "settlement disagrees with the battle result"),
+    ErrorInfo::new(19, "KABLETOP_WRONG_SINCE", "channel is closed before its since lock"),
+
+    // secp256k1 lock (secp256k1_lock.h)
+    ErrorInfo::new(-1, "ERROR_ARGUMENTS_LEN", "secp256k1 lock args have a wrong length"),
+    ErrorInfo::new(-2, "ERROR_ENCODING", "witness isn't a valid WitnessArgs"),
+    ErrorInfo::new(-3, "ERROR_SYSCALL", "a syscall failed during signature verification"),
+    ErrorInfo::new(-11, "ERROR_SECP_RECOVER_PUBKEY", "public key can't be recovered from the signature"),
+    ErrorInfo::new(-12, "ERROR_SECP_VERIFICATION", "signature verification failed"),
+    ErrorInfo::new(-13, "ERROR_SECP_PARSE_PUBKEY", "public key can't be parsed"),
+    ErrorInfo::new(-14, "ERROR_SECP_PARSE_SIGNATURE", "signature can't be parsed"),
+    ErrorInfo::new(-15, "ERROR_SECP_SERIALIZE_PUBKEY", "public key can't be serialized"),
+    ErrorInfo::new(-21, "ERROR_SCRIPT_TOO_LONG", "lock script is too long"),
+    ErrorInfo::new(-22, "ERROR_WITNESS_SIZE", "witness is too large"),
+    ErrorInfo::new(-23, "ERROR_INCORRECT_SINCE_FLAGS", "since flags are incorrect"),
+    ErrorInfo::new(-24, "ERROR_INCORRECT_SINCE_VALUE", "since value is incorrect"),
+    ErrorInfo::new(-31, "ERROR_PUBKEY_BLAKE160_HASH", "public key hash doesn't match the lock args"),
+];
@@ -0,0 +1,135 @@
+#![cfg_attr(not(test), no_std)]
+
+//! Registry of the exit codes returned by every contract. Codes overlap between contracts, so an
+//! exit code only makes sense along with the contract that returned it.
+
+use core::fmt;
+
+mod rust;
+mod kabletop;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Contract {
+    Nft,
+    Payment,
+    Wallet,
+    Kabletop
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorInfo {
+    pub code: i8,
+    pub name: &'static str,
+    pub description: &'static str
+}
+
+impl ErrorInfo {
+    const fn new(code: i8, name: &'static str, description: &'static str) -> Self {
+        ErrorInfo { code, name, description }
+    }
+}
+
+impl fmt::Display for ErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.name, self.code, self.description)
+    }
+}
+
+/// every exit code the contract may return
+pub fn errors(contract: Contract) -> &'static [ErrorInfo] {
+    match contract {
+        Contract::Nft | Contract::Payment | Contract::Wallet => rust::ERRORS,
+        Contract::Kabletop => kabletop::ERRORS
+    }
+}
+
+pub fn lookup(contract: Contract, code: i8) -> Option<&'static ErrorInfo> {
+    errors(contract).iter().find(|error| error.code == code)
+}
+
+pub fn lookup_name(contract: Contract, name: &str) -> Option<&'static ErrorInfo> {
+    errors(contract).iter().find(|error| error.name == name)
+}
+
+/// extracts the exit code from the message of a failed script, e.g. "ValidationFailure(19): ..."
+pub fn exit_code(message: &str) -> Option<i8> {
+    const PREFIX: &str = "ValidationFailure(";
+    let start = message.find(PREFIX)? + PREFIX.len();
+    let end = start + message[start..].find(')')?;
+    message[start..end].parse().ok()
+}
+
+/// asserts that a verification result of ckb-testtool failed with the named error of the contract
+///
+/// ```ignore
+/// assert_script_error!(result, Payment, InsufficientCapacity);
+/// assert_script_error!(result, Kabletop, KABLETOP_WRONG_SINCE);
+/// ```
+#[macro_export]
+macro_rules! assert_script_error {
+    ($result:expr, $contract:ident, $name:ident) => {{
+        let contract = $crate::Contract::$contract;
+        let expected = $crate::lookup_name(contract, stringify!($name))
+            .unwrap_or_else(|| panic!("{} is not registered for {:?}", stringify!($name), contract));
+        let message = match &$result {
+            Ok(_) => panic!("expected {}, but the script passed", expected),
+            Err(err) => format!("{}", err)
+        };
+        match $crate::exit_code(&message) {
+            Some(code) if code == expected.code => (),
+            Some(code) => match $crate::lookup(contract, code) {
+                Some(actual) => panic!("expected {}, but got {}", expected, actual),
+                None => panic!("expected {}, but got unregistered code {}", expected, code)
+            },
+            None => panic!("expected {}, but got {}", expected, message)
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_codes_are_unique() {
+        for &contract in [Contract::Payment, Contract::Kabletop].iter() {
+            let errors = errors(contract);
+            for (i, error) in errors.iter().enumerate() {
+                assert!(error.code != 0, "{} uses the success code", error.name);
+                assert!(errors[i + 1..].iter().all(|other| other.code != error.code), "{} is not unique", error.code);
+                assert!(errors[i + 1..].iter().all(|other| other.name != error.name), "{} is not unique", error.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_by_contract() {
+        assert_eq!(lookup(Contract::Payment, 19).unwrap().name, "InsufficientCapacity");
+        assert_eq!(lookup(Contract::Nft, 19).unwrap().name, "InsufficientCapacity");
+        assert_eq!(lookup(Contract::Kabletop, 19).unwrap().name, "KABLETOP_WRONG_SINCE");
+        assert_eq!(lookup(Contract::Kabletop, -31).unwrap().name, "ERROR_PUBKEY_BLAKE160_HASH");
+        assert_eq!(lookup(Contract::Payment, 100), None);
+        assert_eq!(lookup_name(Contract::Wallet, "CapacityError").unwrap().code, 21);
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let message = "TransactionScriptError { source: Inputs[0].Type, cause: ValidationFailure(-17): the exit code is per script specific }";
+        assert_eq!(exit_code(message), Some(-17));
+        assert_eq!(exit_code("TransactionScriptError { source: Inputs[0].Lock, cause: InvalidCodeHash }"), None);
+    }
+
+    #[test]
+    fn test_assert_script_error() {
+        let result: Result<u64, &str> = Err("ValidationFailure(19): the exit code is per script specific");
+        assert_script_error!(result, Payment, InsufficientCapacity);
+        assert_script_error!(result, Kabletop, KABLETOP_WRONG_SINCE);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected SoldOut (32)")]
+    fn test_assert_script_error_mismatch() {
+        let result: Result<u64, &str> = Err("ValidationFailure(19): the exit code is per script specific");
+        assert_script_error!(result, Payment, SoldOut);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::ErrorInfo;
+
+/// exit codes of `util::error::Error`, shared by the nft, payment and wallet contracts
+pub const ERRORS: &[ErrorInfo] = &[
+    ErrorInfo::new(1, "IndexOutOfBound", "a cell, witness or header was loaded past the end of its source"),
+    ErrorInfo::new(2, "ItemMissing", "a required field, such as a type script or header, is missing"),
+    ErrorInfo::new(3, "LengthNotEnough", "loaded data is longer than the buffer expected by the contract"),
+    ErrorInfo::new(4, "Encoding", "cell data, args or witness is shorter than its layout or malformed"),
+
+    // common
+    ErrorInfo::new(5, "Secp256k1", "secp256k1 signature verification failed"),
+
+    // nft
+    ErrorInfo::new(6, "NFTDataError", "nft collection data is not a list of blake160 hashes"),
+    ErrorInfo::new(7, "NFTTransferError", "output nfts are not covered by the input nfts"),
+
+    // payment
+    ErrorInfo::new(8, "InvalidNFTData", "composer config or nft collection data is malformed"),
+    ErrorInfo::new(9, "InvalidSignature", "transaction is not signed by the expected owner"),
+    ErrorInfo::new(10, "InvalidWalletCreationFormat", "payment cells are created with a non-zero pack counter or bad layout"),
+    ErrorInfo::new(11, "InvalidRevealNFTData", "revealed nfts don't match the lottery of the purchase"),
+    ErrorInfo::new(12, "InvalidTransferFormat", "payment cell is transferred in an unsupported way"),
+    ErrorInfo::new(13, "DumplicateInputCell", "the same ownerlock shows up in several inputs"),
+    ErrorInfo::new(14, "DumplicateOutputCell", "the same ownerlock shows up in several outputs"),
+    ErrorInfo::new(15, "DumplicateDepCell", "the same ownerlock shows up in several cell deps"),
+    ErrorInfo::new(16, "RevealedNFTOutOfBound", "more nfts are revealed than the unrevealed packs contain"),
+    ErrorInfo::new(17, "MissingCells", "output payment cell or composer config dep is absent"),
+    ErrorInfo::new(18, "MissingPaymentHeader", "header of the purchase block is not in header deps"),
+    ErrorInfo::new(19, "InsufficientCapacity", "paid capacity doesn't cover the price of the packs"),
+    ErrorInfo::new(20, "UnknownOperation", "transaction matches none of the supported operations"),
+
+    // wallet
+    ErrorInfo::new(21, "CapacityError", "wallet cells lose capacity in the transaction"),
+
+    // payment, added after the wallet errors
+    ErrorInfo::new(22, "InvalidPaymentData", "payment cell data is malformed or uses an unknown version"),
+    ErrorInfo::new(23, "PackCounterOverflow", "pack counter or price arithmetic overflows"),
+    ErrorInfo::new(24, "PackCounterUnderflow", "more packs are revealed than were bought"),
+    ErrorInfo::new(25, "MissingRevealHeader", "header of the reveal block is not in header deps"),
+    ErrorInfo::new(26, "SeriesMismatch", "packs of different series are mixed or the config series differs"),
+    ErrorInfo::new(27, "InsufficientSUDTAmount", "paid sUDT amount doesn't cover the price of the packs"),
+    ErrorInfo::new(28, "RefundOutOfBound", "refund exceeds what was paid for the unrevealed packs"),
+    ErrorInfo::new(29, "RefundNotMatured", "refund is claimed before the refund delay has passed or while the packs can still be revealed"),
+    ErrorInfo::new(30, "MissingSaleCell", "limited sale is bought without its sale cell"),
+    ErrorInfo::new(31, "InvalidSaleData", "sale cell data is malformed or updated inconsistently"),
+    ErrorInfo::new(32, "SoldOut", "limited sale has no packs left"),
+    ErrorInfo::new(33, "SalePaused", "sale window is paused by the composer"),
+    ErrorInfo::new(34, "SaleWindowClosed", "purchase happens outside of the sale window"),
+    ErrorInfo::new(35, "PurchaseLimitExceeded", "buyer exceeds the packs allowed per buyer"),
+    ErrorInfo::new(36, "InvalidWhitelistProof", "whitelist proof doesn't match the whitelist root"),
+    ErrorInfo::new(37, "InvalidGiftOperation", "gift lowers the recipient's capacity or reveals packs"),
+    ErrorInfo::new(38, "InvalidSealedPackData", "sealed pack cell data is malformed or forged"),
+    ErrorInfo::new(39, "ConfigMismatch", "draw rules of the composer config differ from the ones snapshotted at purchase"),
+    ErrorInfo::new(40, "InvalidOperatorOperation", "operator changes more than the composer configs allow"),
+    ErrorInfo::new(41, "PartialRevealInProgress", "packs are bought while a partial reveal is unfinished"),
+    ErrorInfo::new(42, "UnregisteredPaymentCell", "packs capped per buyer are bought into a payment cell the composer didn't register"),
+];
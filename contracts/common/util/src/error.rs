@@ -1,37 +1,63 @@
 use ckb_std::error::SysError;
 
 /// Error
+///
+/// discriminants are the exit codes seen by clients, so they are never renumbered or reused:
+/// new errors are appended with the next code and registered in the `error-registry` crate
 #[repr(i8)]
 pub enum Error {
     IndexOutOfBound = 1,
-    ItemMissing,
-    LengthNotEnough,
-    Encoding,
+    ItemMissing = 2,
+    LengthNotEnough = 3,
+    Encoding = 4,
 
 	// common
-    Secp256k1,
+    Secp256k1 = 5,
 
     // nft
-    NFTDataError,
-    NFTTransferError,
+    NFTDataError = 6,
+    NFTTransferError = 7,
 
     // payment
-    InvalidNFTData,
-    InvalidSignature,
-    InvalidWalletCreationFormat,
-    InvalidRevealNFTData,
-    InvalidTransferFormat,
-    DumplicateInputCell,
-    DumplicateOutputCell,
-    DumplicateDepCell,
-    RevealedNFTOutOfBound,
-    MissingCells,
-    MissingPaymentHeader,
-    InsufficientCapacity,
-    UnknownOperation,
+    InvalidNFTData = 8,
+    InvalidSignature = 9,
+    InvalidWalletCreationFormat = 10,
+    InvalidRevealNFTData = 11,
+    InvalidTransferFormat = 12,
+    DumplicateInputCell = 13,
+    DumplicateOutputCell = 14,
+    DumplicateDepCell = 15,
+    RevealedNFTOutOfBound = 16,
+    MissingCells = 17,
+    MissingPaymentHeader = 18,
+    InsufficientCapacity = 19,
+    UnknownOperation = 20,
 
     // wallet
-    CapacityError
+    CapacityError = 21,
+
+    // payment, added after the wallet errors
+    InvalidPaymentData = 22,
+    PackCounterOverflow = 23,
+    PackCounterUnderflow = 24,
+    MissingRevealHeader = 25,
+    SeriesMismatch = 26,
+    InsufficientSUDTAmount = 27,
+    RefundOutOfBound = 28,
+    RefundNotMatured = 29,
+    MissingSaleCell = 30,
+    InvalidSaleData = 31,
+    SoldOut = 32,
+    SalePaused = 33,
+    SaleWindowClosed = 34,
+    PurchaseLimitExceeded = 35,
+    InvalidWhitelistProof = 36,
+    InvalidGiftOperation = 37,
+    InvalidSealedPackData = 38,
+    ConfigMismatch = 39,
+    InvalidOperatorOperation = 40,
+    PartialRevealInProgress = 41,
+    UnregisteredPaymentCell = 42
 }
 
 impl From<SysError> for Error {
@@ -46,3 +72,47 @@ impl From<SysError> for Error {
         }
     }
 }
+
+impl From<codec::Error> for Error {
+    fn from(err: codec::Error) -> Self {
+        match err {
+            codec::Error::Encoding => Self::Encoding,
+            codec::Error::InvalidNFTData => Self::InvalidNFTData,
+            codec::Error::InvalidPaymentData => Self::InvalidPaymentData,
+            codec::Error::InvalidSaleData => Self::InvalidSaleData,
+            codec::Error::InvalidSealedPackData => Self::InvalidSealedPackData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error_registry::{errors, lookup, Contract};
+
+    macro_rules! registered {
+        ($($name:ident),*) => {
+            [$((Error::$name as i8, stringify!($name))),*]
+        };
+    }
+
+    #[test]
+    fn test_error_codes_are_registered() {
+        let codes = registered!(
+            IndexOutOfBound, ItemMissing, LengthNotEnough, Encoding, Secp256k1, NFTDataError,
+            NFTTransferError, InvalidNFTData, InvalidSignature, InvalidWalletCreationFormat,
+            InvalidRevealNFTData, InvalidTransferFormat, DumplicateInputCell, DumplicateOutputCell,
+            DumplicateDepCell, RevealedNFTOutOfBound, MissingCells, MissingPaymentHeader,
+            InsufficientCapacity, UnknownOperation, InvalidPaymentData, PackCounterOverflow,
+            PackCounterUnderflow, MissingRevealHeader, SeriesMismatch, InsufficientSUDTAmount,
+            RefundOutOfBound, RefundNotMatured, MissingSaleCell, InvalidSaleData, SoldOut, SalePaused,
+            SaleWindowClosed, PurchaseLimitExceeded, InvalidWhitelistProof, InvalidGiftOperation,
+            InvalidSealedPackData, ConfigMismatch, InvalidOperatorOperation,
+            PartialRevealInProgress, UnregisteredPaymentCell, CapacityError
+        );
+        for &(code, name) in codes.iter() {
+            assert_eq!(lookup(Contract::Payment, code).map(|error| error.name), Some(name));
+        }
+        assert_eq!(errors(Contract::Payment).len(), codes.len());
+    }
+}
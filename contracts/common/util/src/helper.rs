@@ -1,75 +1,98 @@
 extern crate alloc;
-use alloc::{vec::Vec, vec};
-use core::{convert::TryInto, mem::size_of};
+use alloc::{collections::BTreeMap, vec::Vec, vec};
 use ckb_std::ckb_types::{packed::Header, bytes::Bytes};
+use crate::error::Error;
 
-pub type Blake160 = [u8; 20];
+pub use codec::{Blake160, Blake256, StreamFetcher};
 
-pub struct StreamFetcher<'load> {
-    pub index: usize,
-    pub stream: &'load Vec<u8>
-}
-
-impl<'load> StreamFetcher<'load> {
-    fn next<T>(&mut self) -> (usize, usize) {
-        let s = self.index;
-        let e = s + size_of::<T>();
-        self.index = e;
-        return (s, e);
-    }
-
-    pub fn get_u64(&mut self) -> u64 {
-        let (s, e) = self.next::<u64>();
-        return u64::from_le_bytes(self.stream[s..e].try_into().unwrap());
-    }
+pub type DataCapPair = Option<(Vec<u8>, u64)>;
+pub type VerifyDataKey = [u8; 32];
 
-    pub fn get_u8(&mut self) -> u8 {
-        let (s, e) = self.next::<u8>();
-        return u8::from_le_bytes(self.stream[s..e].try_into().unwrap());
-    }
+// cells of one payment script group that share an ownerlock, with the composer's config cells for it
+pub struct OwnerlockEntry {
+    pub ownerlock_args: Bytes,
+    pub input: DataCapPair,
+    pub output: DataCapPair,
+    pub nft_configs: Vec<Vec<u8>>,
+    pub header: Option<Header>
+}
 
-    pub fn get_blake160(&mut self) -> Blake160 {
-        let (s, e) = self.next::<Blake160>();
-        return self.stream[s..e].try_into().unwrap();
+impl OwnerlockEntry {
+    pub fn new(ownerlock_args: Bytes) -> Self {
+        OwnerlockEntry { ownerlock_args, input: None, output: None, nft_configs: vec![], header: None }
     }
 }
 
-pub type DataCapPair = Option<(Vec<u8>, u64)>;
-pub type VerifyDataKey = [u8; 32];
-pub type VerifyDataValue = (Bytes, DataCapPair, DataCapPair, Vec<u8>, Option<Header>);
-
+// entries are kept sorted by ownerlock hash, so lookups stay logarithmic however many wallets join
 pub struct VerifyDataMap {
-    pub data: Vec<(VerifyDataKey, VerifyDataValue)>
+    data: BTreeMap<VerifyDataKey, OwnerlockEntry>
 }
 
 impl VerifyDataMap {
     pub fn new() -> VerifyDataMap {
-        VerifyDataMap{
-            data: vec![]
+        VerifyDataMap {
+            data: BTreeMap::new()
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     pub fn contains_key(&self, key: &VerifyDataKey) -> bool {
-        self.data
-            .iter()
-            .filter(|(k, _)| k[..] == key[..])
-            .count() > 0
+        self.data.contains_key(key)
     }
 
-    pub fn insert(&mut self, key: VerifyDataKey, value: VerifyDataValue) {
-        if self.contains_key(&key) {
-            panic!("insert key exists");
+    pub fn insert(&mut self, key: VerifyDataKey, value: OwnerlockEntry) -> Result<(), Error> {
+        if self.data.contains_key(&key) {
+            return Err(Error::DumplicateInputCell);
         }
-        self.data.push((key, value));
+        self.data.insert(key, value);
+        return Ok(());
+    }
+
+    pub fn get_mut(&mut self, key: &VerifyDataKey) -> Option<&mut OwnerlockEntry> {
+        self.data.get_mut(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&VerifyDataKey, &OwnerlockEntry)> {
+        self.data.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_rejects_duplicate_ownerlock() {
+        let mut map = VerifyDataMap::new();
+        assert!(map.insert([1u8; 32], OwnerlockEntry::new(Bytes::from(vec![1u8; 20]))).is_ok());
+        assert!(matches!(
+            map.insert([1u8; 32], OwnerlockEntry::new(Bytes::from(vec![2u8; 20]))),
+            Err(Error::DumplicateInputCell)
+        ));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get_mut(&[1u8; 32]).unwrap().ownerlock_args[..], [1u8; 20]);
     }
 
-    pub fn get_mut(&mut self, key: &VerifyDataKey) -> Option<&mut VerifyDataValue> {
-        let mut value = None;
-        for (k, v) in self.data.iter_mut() {
-            if k[..] == key[..] {
-                value = Some(v);
-            }
+    #[test]
+    fn test_map_updates_and_iterates_in_key_order() {
+        let mut map = VerifyDataMap::new();
+        for key in [3u8, 1, 2].iter() {
+            assert!(map.insert([*key; 32], OwnerlockEntry::new(Bytes::from(vec![*key; 20]))).is_ok());
         }
-        value
+        map.get_mut(&[2u8; 32]).unwrap().output = Some((vec![0u8], 100));
+        assert!(map.get_mut(&[4u8; 32]).is_none());
+        assert!(map.contains_key(&[3u8; 32]));
+        let keys = map.iter().map(|(key, _)| key[0]).collect::<Vec<u8>>();
+        assert_eq!(keys, vec![1u8, 2, 3]);
+        let (_, entry) = map.iter().nth(1).unwrap();
+        assert_eq!(entry.output, Some((vec![0u8], 100)));
+        assert!(entry.input.is_none());
     }
 }
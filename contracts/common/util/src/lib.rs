@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![allow(dead_code)]
 #![feature(lang_items)]
 #![feature(alloc_error_handler)]
@@ -6,3 +6,54 @@
 
 pub mod error;
 pub mod helper;
+
+// ckb-std links a libc stub whose malloc always fails, so host tests allocate from a locked static heap
+#[cfg(test)]
+mod test_alloc {
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use ckb_std::buddy_alloc::{NonThreadsafeAlloc, FastAllocParam, BuddyAllocParam};
+
+    const FIXED_BLOCK_HEAP_SIZE: usize = 64 * 1024;
+    const HEAP_SIZE: usize = 16 * 1024 * 1024;
+    static mut FIXED_BLOCK_HEAP: [u8; FIXED_BLOCK_HEAP_SIZE] = [0u8; FIXED_BLOCK_HEAP_SIZE];
+    static mut HEAP: [u8; HEAP_SIZE] = [0u8; HEAP_SIZE];
+
+    struct LockedAlloc(AtomicBool, NonThreadsafeAlloc);
+
+    impl LockedAlloc {
+        fn lock(&self) {
+            while self.0.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {}
+        }
+
+        fn unlock(&self) {
+            self.0.store(false, Ordering::Release);
+        }
+    }
+
+    unsafe impl GlobalAlloc for LockedAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            self.lock();
+            let ptr = self.1.alloc(layout);
+            self.unlock();
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.lock();
+            self.1.dealloc(ptr, layout);
+            self.unlock();
+        }
+    }
+
+    #[global_allocator]
+    static ALLOC: LockedAlloc = unsafe {
+        LockedAlloc(
+            AtomicBool::new(false),
+            NonThreadsafeAlloc::new(
+                FastAllocParam::new(FIXED_BLOCK_HEAP.as_ptr(), FIXED_BLOCK_HEAP_SIZE),
+                BuddyAllocParam::new(HEAP.as_ptr(), HEAP_SIZE, 64)
+            )
+        )
+    };
+}
@@ -1,8 +1,5 @@
 // Import from `core` instead of from `std` since we are in no-std mode
-use core::{
-    result::Result,
-    convert::TryInto,
-};
+use core::result::Result;
 
 // Import heap related library from `alloc`
 // https://doc.rust-lang.org/alloc/index.html
@@ -18,6 +15,7 @@ use ckb_std::{
 };
 
 use util::{error::Error, helper::Blake160};
+use codec::collection::decode_nft_collection;
 
 pub fn main() -> Result<(), Error> {
     let script = load_script()?;
@@ -56,19 +54,10 @@ fn check_owner_mode(args: &Bytes) -> bool {
 
 fn collect_nfts(source: Source) -> Result<Vec<Blake160>, Error> {
     let mut nfts: Vec<Blake160> = vec![];
-    QueryIter::new(load_cell_data, source)
-        .map(|data| {
-            if data.len() % 20 != 0 {
-                return Err(Error::NFTDataError);
-            }
-            for i in 0..(data.len() / 20 - 1) {
-                let s = i * 20;
-                let e = (i + 1) * 20;
-                nfts.push(data[s..e].try_into().unwrap());
-            }
-            return Ok(());
-        })
-        .collect::<Result<Vec<_>, Error>>()?;
+    for data in QueryIter::new(load_cell_data, source) {
+        let mut nft_collection = decode_nft_collection(&data).map_err(|_| Error::NFTDataError)?;
+        nfts.append(&mut nft_collection);
+    }
     return Ok(nfts);
 }
 
@@ -2,7 +2,8 @@
 use core::{
     result::Result,
     mem::size_of,
-    convert::TryInto
+    convert::TryInto,
+    ops::Deref
 };
 
 // Import heap related library from `alloc`
@@ -27,6 +28,13 @@ use ckb_std::{
 };
 use secp256k1::{recover_pubkey_hash, digest};
 use util::{error::Error, helper::*};
+use codec::{
+    config::{NftConfig as ConfigParams, SaleWindow, REVENUE_SHARE_TOTAL},
+    payment::*,
+    sale::{SaleArgs, SALE_ARGS_SIZE, decode_sale_stock},
+    sealed_pack::{SealedPack, SEALED_PACK_ARGS_SIZE},
+    collection::decode_nft_collection
+};
 
 pub fn main() -> Result<(), Error> {
     // check script args
@@ -35,71 +43,132 @@ pub fn main() -> Result<(), Error> {
         return Err(Error::Encoding);
     }
 
-    // recover pubkey from signature
-    let pubkey_hash = get_signature_pubkey_hash()?;
+    // sale cell of a limited-edition series is shared by every buyer in purchase transactions
+    if args.len() == SALE_ARGS_SIZE {
+        return check_sale_operation(&args);
+    }
+
+    // sealed packs are transferred or revealed by their holders, who don't need a payment cell
+    if args.len() == SEALED_PACK_ARGS_SIZE {
+        return check_sealed_pack_operation(&args);
+    }
+
+    // recover pubkey from signature, which is optional for gift purchases
+    let pubkey_hash_opt = get_signature_pubkey_hash().ok();
 
     // collect cell_data to prepare verify
     let verify_data = collect_verifydata_by_ownerlockhash()?;
-    // debug!("verify_data = {:?}", verify_data.data);
 
-    // check if nft composer called this script
-    if check_sudo_mode(&args, &pubkey_hash, &verify_data)? {
-        return Ok(());
+    // check if the operator named by composer's config cells called this script
+    if let Some(pubkey_hash) = &pubkey_hash_opt {
+        if pubkey_hash[..] != args[..] && check_operator_mode(&args, pubkey_hash, &verify_data)? {
+            return Ok(());
+        }
     }
 
+    // anyone other than the buyer can only fund purchases into the buyer's payment cell
+    let gift_mode = match pubkey_hash_opt {
+        Some(pubkey_hash) => pubkey_hash[..] != args[..],
+        None => true
+    };
+
     // verify transaction format when it's guest mode
-    for (lock_hash, (_, old_opt, new_opt, dep_data, header_opt)) in verify_data.data.iter() {
+    let mut purchased = false;
+    for (lock_hash, entry) in verify_data.iter() {
+        // check if nft composer called this script for the cells of their own wallet
+        if check_sudo_mode(&lock_hash, &args, &pubkey_hash_opt, entry)? {
+            continue;
+        }
+        let OwnerlockEntry { ownerlock_args, input: old_opt, output: new_opt, nft_configs: dep_data, header: header_opt } = entry;
         // tx that guests firstly contain the payment contract must be a WALLET CREATION tx
         // which input cells that fill with ownerlock lock_script will be EMPTY
         if old_opt.is_none() {
-            let mut ok = false;
-            if let Some((new_data, _)) = new_opt {
-                // the data in wallet creation tx must be a ZERO byte
-                if new_data.len() == 1 && new_data[0] == 0u8 {
-                    ok = true;
-                }
-            }
-            if !ok {
-                return Err(Error::InvalidWalletCreationFormat);
+            check_wallet_creation(new_opt, false)?;
+            continue;
+        }
+
+        // output_cell must be applied while in guest mode
+        if new_opt.is_none() {
+            return Err(Error::MissingCells);
+        }
+
+        // check REFUND operation, which takes composer's config cell to prove the packs can't be revealed
+        if gift_mode {
+            let (_, old_ckb) = old_opt.as_ref().unwrap();
+            let (_, new_ckb) = new_opt.as_ref().unwrap();
+            if new_ckb < old_ckb {
+                return Err(Error::InvalidGiftOperation);
             }
-            break;
+        } else if check_refund_operation(&lock_hash, &old_opt, &new_opt, &dep_data, &header_opt)? {
+            check_no_minted_sealed_packs(&lock_hash)?;
+            continue;
         }
 
-        // composer nft_config_dep_cell and output_cell must be applied while in guest mode
-        if dep_data.is_empty() || new_opt.is_none() {
-            return Err(Error::MissingCells)
+        // composer nft_config_dep_cell must be applied for other operations
+        if dep_data.is_empty() {
+            return Err(Error::MissingCells);
         }
 
-        // prepare composer's nft issuance regulation
-        let (ckb_price_perpack, nft_count_perpack, nft_config) = parse_nft_params(dep_data)?;
+        // prepare composer's nft issuance regulation of the series that buyer's packs belong to
+        let (old_payment, new_payment) = parse_payment_pair(&old_opt, &new_opt)?;
+        let series_id = get_payment_series(&old_payment, &new_payment)?;
+        let nft_config = match get_config_snapshot(&old_payment) {
+            Some(config_hash) => find_nft_params_by_hash(dep_data, &config_hash)?,
+            None => find_nft_params(dep_data, series_id)?
+        };
 
         // check PAYMENT operation
-        let payment_op = check_payment_operation(&old_opt, &new_opt, ckb_price_perpack)?;
+        let payment_op = check_payment_operation(&lock_hash, &ownerlock_args, &old_opt, &new_opt, &header_opt, &nft_config)?;
+        purchased |= payment_op;
+
+        // gift can't reveal packs for the buyer
+        if gift_mode {
+            if !payment_op {
+                return Err(Error::InvalidGiftOperation);
+            }
+            continue;
+        }
 
         // check REVEAL operation
-        let reveal_op = check_reveal_operation(&lock_hash, &old_opt, &new_opt, &header_opt,
-            nft_count_perpack as usize, &nft_config)?;
+        let reveal_op = check_reveal_operation(&lock_hash, &args, &old_opt, &new_opt, &header_opt, &nft_config)?;
 
         // tx format crashed
         if !payment_op && !reveal_op {
             return Err(Error::UnknownOperation);
         }
     }
-    
+
+    // check what the purchases pay, once for all of them
+    if purchased {
+        check_required_payments()?;
+    }
+
     Ok(())
 }
 
 const CKB_SOURCE_INPUT: u64 = 1;
 
+// every buyer signs the witness of their first payment input, so that one transaction can batch
+// purchases and reveals of many buyers, each checked by the payment script group of their own
 fn get_signature_pubkey_hash() -> Result<Blake160, Error> {
-    let mut index: u64 = 0;
+    let index = match find_signature_input_index()? {
+        Some(value) => value,
+        None => return Err(Error::InvalidSignature)
+    };
+    let (pubkey_hash, ok) = recover_pubkey_hash(index, CKB_SOURCE_INPUT);
+    if !ok {
+        return Err(Error::Secp256k1);
+    }
+    return Ok(pubkey_hash);
+}
+
+fn find_signature_input_index() -> Result<Option<u64>, Error> {
     let hash = load_script_hash()?;
     for i in 0.. {
         match load_cell_type_hash(i, Source::Input) {
             Ok(value_opt) => match value_opt {
                 Some(value) => if value[..] == hash[..] {
-                    index = i.try_into().unwrap();
-                    break;
+                    return Ok(Some(i.try_into().unwrap()));
                 },
                 None => continue
             },
@@ -107,12 +176,33 @@ fn get_signature_pubkey_hash() -> Result<Blake160, Error> {
             Err(err) => return Err(Error::from(err))
         }
     }
-
-    let (pubkey_hash, ok) = recover_pubkey_hash(index, CKB_SOURCE_INPUT);
-    if !ok {
-        return Err(Error::Secp256k1);
+    // output-only group (wallet or config creation) is signed on an input of the signer's own lock
+    let args: Bytes = load_script()?.args().unpack();
+    for i in 0.. {
+        match load_cell_lock(i, Source::Input) {
+            Ok(lock) => if get_ownerlock_pubkey_hash(&lock.args().unpack())[..] == args[..] {
+                return Ok(Some(i.try_into().unwrap()));
+            },
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        }
     }
-    return Ok(pubkey_hash);
+    // or, when the composer registers the wallet, on an input of the wallet's ownerlock
+    let ownerlock_hash = match load_cell_lock_hash(0, Source::GroupOutput) {
+        Ok(value) => value,
+        Err(SysError::IndexOutOfBound) => return Ok(None),
+        Err(err) => return Err(Error::from(err))
+    };
+    for i in 0.. {
+        match load_cell_lock_hash(i, Source::Input) {
+            Ok(lock_hash) => if lock_hash[..] == ownerlock_hash[..] {
+                return Ok(Some(i.try_into().unwrap()));
+            },
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        }
+    }
+    return Ok(None);
 }
 
 fn collect_verifydata_by_ownerlockhash() -> Result<VerifyDataMap, Error> {
@@ -125,10 +215,6 @@ fn collect_verifydata_by_ownerlockhash() -> Result<VerifyDataMap, Error> {
             Err(err) => return Err(Error::from(err))
         };
         let lock_hash = load_cell_lock_hash(i, Source::GroupInput)?;
-        // every payment contract could match only one type of ownerlock (specially different lock_args)
-        if verify_data.contains_key(&lock_hash) {
-            return Err(Error::DumplicateInputCell);
-        }
         // if it's in the reveal mode, one ownerlock input should pair with it's sealed block header
         let header = match load_header(i, Source::GroupInput) {
             Ok(value) => Some(value),
@@ -139,7 +225,11 @@ fn collect_verifydata_by_ownerlockhash() -> Result<VerifyDataMap, Error> {
         // make an input data-capacity pair
         let data = load_cell_data(i, Source::GroupInput)?;
         let capacity = load_cell_capacity(i, Source::GroupInput)?;
-        verify_data.insert(lock_hash, (lock.args().unpack(), Some((data, capacity)), None, vec![], header));
+        // every payment contract could match only one type of ownerlock (specially different lock_args)
+        let mut entry = OwnerlockEntry::new(lock.args().unpack());
+        entry.input = Some((data, capacity));
+        entry.header = header;
+        verify_data.insert(lock_hash, entry)?;
     }
     // collect and match output
     for i in 0.. {
@@ -152,13 +242,17 @@ fn collect_verifydata_by_ownerlockhash() -> Result<VerifyDataMap, Error> {
         let data = load_cell_data(i, Source::GroupOutput)?;
         let capacity = load_cell_capacity(i, Source::GroupOutput)?;
         match verify_data.get_mut(&lock_hash) {
-            Some((_, _, output_opt, _, _)) => {
-                if output_opt.is_some() {
+            Some(entry) => {
+                if entry.output.is_some() {
                     return Err(Error::DumplicateOutputCell);
                 }
-                *output_opt = Some((data, capacity));
+                entry.output = Some((data, capacity));
             },
-            None => { verify_data.insert(lock_hash, (lock.args().unpack(), None, Some((data, capacity)), vec![], None)); }
+            None => {
+                let mut entry = OwnerlockEntry::new(lock.args().unpack());
+                entry.output = Some((data, capacity));
+                verify_data.insert(lock_hash, entry)?;
+            }
         };
     }
     // collect and match cell_dep
@@ -175,18 +269,21 @@ fn collect_verifydata_by_ownerlockhash() -> Result<VerifyDataMap, Error> {
         // if it's in guest mode, make sure we have prepared composer's nft config cell
         if type_opt.is_none()
             || type_opt.unwrap().code_hash().raw_data()[..] != code_hash.raw_data()[..]
-            || lock.args().raw_data()[..] != type_opt.unwrap().args().raw_data()[..] {
+            || get_ownerlock_pubkey_hash(&lock.args().raw_data())[..] != type_opt.unwrap().args().raw_data()[..] {
             continue;
         }
         let lock_hash = load_cell_lock_hash(i, Source::CellDep)?;
         match verify_data.get_mut(&lock_hash) {
-            Some((_, _, _, nft_config_data, _)) => {
-                if nft_config_data.len() > 0 {
-                    return Err(Error::DumplicateDepCell);
+            Some(entry) => {
+                // composer can run several nft series side by side, but only one config cell per series
+                let cell_data = load_cell_data(i, Source::CellDep)?;
+                let series_id = parse_nft_params(&cell_data)?.series_id;
+                for nft_config_data in entry.nft_configs.iter() {
+                    if parse_nft_params(nft_config_data)?.series_id == series_id {
+                        return Err(Error::DumplicateDepCell);
+                    }
                 }
-                let mut cell_data = load_cell_data(i, Source::CellDep)?;
-                parse_nft_params(&cell_data)?;
-                nft_config_data.append(&mut cell_data);
+                entry.nft_configs.push(cell_data);
             },
             None => {}//return Err(Error::UnusedDepCell)
         };
@@ -194,170 +291,1191 @@ fn collect_verifydata_by_ownerlockhash() -> Result<VerifyDataMap, Error> {
     Ok(verify_data)
 }
 
-fn check_sudo_mode(payment_args: &Bytes, pubkey_hash: &Blake160, verify_data: &VerifyDataMap) -> Result<bool, Error> {
-    for (_, (ownerlock_args, input_opt, output_opt, _, _)) in verify_data.data.iter() {
-        // wallet owner call script
-        if pubkey_hash[..] == payment_args[..] {
-            // sudo mode
-            if ownerlock_args[..] == payment_args[..] {
-                if let Some((data, _)) = input_opt {
-                    parse_nft_params(&data)?;
-                }
-                if let Some((data, _)) = output_opt {
-                    parse_nft_params(&data)?;
+fn check_sudo_mode(lock_hash: &[u8; 32], payment_args: &Bytes, pubkey_hash_opt: &Option<Blake160>,
+        entry: &OwnerlockEntry) -> Result<bool, Error> {
+    let pubkey_hash = match pubkey_hash_opt {
+        Some(value) => value,
+        None => return Ok(false)
+    };
+    let OwnerlockEntry { ownerlock_args, input: input_opt, output: output_opt, header: header_opt, .. } = entry;
+    // neither the buyer nor the composer owns the cells of this wallet
+    if get_ownerlock_pubkey_hash(ownerlock_args)[..] != pubkey_hash[..] {
+        return Ok(false);
+    }
+    check_no_minted_sealed_packs(lock_hash)?;
+    // wallet owner call script (sudo mode)
+    if pubkey_hash[..] == payment_args[..] {
+        if let Some((data, _)) = input_opt {
+            parse_nft_params(&data)?;
+        }
+        if let Some((data, _)) = output_opt {
+            parse_nft_params(&data)?;
+        }
+        return Ok(true);
+    }
+    // nft composer call script, which registers the one payment cell of a buyer when creating it
+    if input_opt.is_none() {
+        check_wallet_creation(output_opt, true)?;
+        return Ok(true);
+    }
+    // otherwise the composer can only transfer ckb from this wallet, which records the purchase block
+    // of unrevealed packs the first time, since moving the payment cell must not move their lottery
+    if let Some((input_data, _)) = input_opt {
+        if let Some((output_data, output_ckb)) = output_opt {
+            let payment = parse_payment_data(input_data)?;
+            let anchor_block = get_purchase_anchor(&payment, header_opt)?;
+            let transfer_data = if anchor_block != payment.anchor_block {
+                if payment.version < PAYMENT_DATA_VERSION_PARTIAL {
+                    return Err(Error::InvalidTransferFormat);
                 }
-                return Ok(true);
-            // guest mode
+                PaymentData { anchor_block, ..payment }.encode()
             } else {
-                return Ok(false);
-            }
-        // nft composer call script (can only transfer ckb from this wallet)
-        } else if ownerlock_args[..] == pubkey_hash[..] {
-            if let Some((input_data, _)) = input_opt {
-                if let Some((output_data, _)) = output_opt {
-                    if input_data[..] == output_data[..] {
-                        return Ok(true);
-                    } 
-                }
+                input_data.clone()
+            };
+            if transfer_data[..] == output_data[..] && check_withdrawable_capacity(lock_hash, output_data, *output_ckb)? {
+                return Ok(true);
             }
-            return Err(Error::InvalidTransferFormat);
         }
     }
-    return Err(Error::InvalidSignature);
+    return Err(Error::InvalidTransferFormat);
+}
+
+// capacity paid for unrevealed packs stays refundable to the buyer, so the composer can only
+// withdraw what exceeds it
+fn check_withdrawable_capacity(lock_hash: &[u8; 32], data: &Vec<u8>, output_ckb: u64) -> Result<bool, Error> {
+    let payment = parse_payment_data(data)?;
+    if payment.count == 0 || payment.version < PAYMENT_DATA_VERSION_REFUNDABLE {
+        return Ok(true);
+    }
+    for i in 0.. {
+        let output_lock_hash = match load_cell_lock_hash(i, Source::GroupOutput) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if output_lock_hash[..] != lock_hash[..] {
+            continue;
+        }
+        let reserved = load_cell_occupied_capacity(i, Source::GroupOutput)?
+            .checked_add(payment.paid)
+            .ok_or(Error::InsufficientCapacity)?;
+        return Ok(output_ckb >= reserved);
+    }
+    return Ok(false);
+}
+
+// operator named by a config cell can edit it in place of the composer, but can neither create, delete
+// nor withdraw from config cells, can't hand the operator role to anyone else, and can't redirect
+// the revenue or swap the series and the nft contract its packs are revealed into
+fn check_operator_mode(payment_args: &Bytes, pubkey_hash: &Blake160, verify_data: &VerifyDataMap) -> Result<bool, Error> {
+    let is_operator = verify_data.iter().any(|(_, OwnerlockEntry { ownerlock_args, input: input_opt, .. })| {
+        match input_opt {
+            Some((data, _)) => get_ownerlock_pubkey_hash(ownerlock_args)[..] == payment_args[..]
+                && parse_nft_params(data).map_or(false, |config| config.operator == Some(*pubkey_hash)),
+            None => false
+        }
+    });
+    if !is_operator {
+        return Ok(false);
+    }
+    for (lock_hash, OwnerlockEntry { ownerlock_args, input: input_opt, output: output_opt, .. }) in verify_data.iter() {
+        if get_ownerlock_pubkey_hash(ownerlock_args)[..] != payment_args[..] {
+            return Err(Error::InvalidOperatorOperation);
+        }
+        check_no_minted_sealed_packs(lock_hash)?;
+        let ((input_data, input_ckb), (output_data, output_ckb)) = match (input_opt, output_opt) {
+            (Some(input), Some(output)) => (input, output),
+            _ => return Err(Error::InvalidOperatorOperation)
+        };
+        let old_config = parse_nft_params(input_data)?;
+        let new_config = parse_nft_params(output_data)?;
+        if old_config.operator != Some(*pubkey_hash) || new_config.operator != old_config.operator || output_ckb < input_ckb {
+            return Err(Error::InvalidOperatorOperation);
+        }
+        if new_config.revenue_split != old_config.revenue_split
+            || new_config.series_id != old_config.series_id
+            || new_config.nft_code_hash != old_config.nft_code_hash
+            || new_config.nft_hash_type != old_config.nft_hash_type {
+            return Err(Error::InvalidOperatorOperation);
+        }
+    }
+    return Ok(true);
+}
+
+// the data in wallet creation tx must be an EMPTY pack counter, which only the composer can register
+fn check_wallet_creation(new_opt: &DataCapPair, composer_signed: bool) -> Result<(), Error> {
+    if let Some((new_data, _)) = new_opt {
+        let new_payment = parse_payment_data(new_data)?;
+        if new_payment.count == 0 && new_payment.paid == 0 && new_payment.packs_since_hit == 0
+            && new_payment.lifetime_count == 0 && new_payment.config_hash == [0u8; 32]
+            && new_payment.draw_offset == 0 && new_payment.anchor_block == 0
+            && (composer_signed || !new_payment.registered) {
+            return Ok(());
+        }
+    }
+    return Err(Error::InvalidWalletCreationFormat);
+}
+
+// ownerlock args: composer_pubkey_hash(blake160) | delegate_code_hash(blake256, optional)
+fn get_ownerlock_pubkey_hash(ownerlock_args: &Bytes) -> Bytes {
+    if ownerlock_args.len() > size_of::<Blake160>() {
+        return ownerlock_args.slice(..size_of::<Blake160>());
+    }
+    return ownerlock_args.clone();
 }
 
-fn check_payment_operation(old_opt: &DataCapPair, new_opt: &DataCapPair, ckb_price: u64) -> Result<bool, Error> {
-    let (old_data, old_ckb) = old_opt.as_ref().unwrap();
-    let (new_data, new_ckb) = new_opt.as_ref().unwrap();
+fn check_payment_operation(lock_hash: &[u8; 32], ownerlock_args: &Bytes, old_opt: &DataCapPair, new_opt: &DataCapPair,
+        header_opt: &Option<Header>, nft_config: &NftConfig) -> Result<bool, Error> {
+    let (_, old_ckb) = old_opt.as_ref().unwrap();
+    let (_, new_ckb) = new_opt.as_ref().unwrap();
+    let (old_payment, new_payment) = parse_payment_pair(old_opt, new_opt)?;
 
-    if old_data.len() == 1 && new_data.len() == 1 && old_data[0] == 0u8 {
-        let buy_count = new_data[0] - old_data[0];
-        let payment = new_ckb - old_ckb;
-        if payment < buy_count as u64 * ckb_price {
+    // packs minted into sealed pack cells are paid by the payment cell of the same ownerlock
+    let minted_count = sum_minted_packs(lock_hash, nft_config)?;
+    if minted_count > 0 && new_payment.count != old_payment.count {
+        return Err(Error::InvalidSealedPackData);
+    }
+
+    // packs can be bought on top of any unrevealed ones, only the increment should be paid
+    if new_payment.count > old_payment.count || minted_count > 0 {
+        let buy_count = new_payment.count - old_payment.count + minted_count;
+        // packs bought on top of a partial reveal would be drawn from a lottery the buyer already knows
+        if old_payment.draw_offset != 0 || new_payment.draw_offset != 0 {
+            return Err(Error::PartialRevealInProgress);
+        }
+        // packs bought on top of unrevealed ones join the lottery of the first purchase, so its block
+        // is recorded instead of being moved to this one
+        if new_payment.anchor_block != get_purchase_anchor(&old_payment, header_opt)? {
+            return Err(Error::InvalidPaymentData);
+        }
+        // pity counter only moves while revealing
+        if new_payment.packs_since_hit != old_payment.packs_since_hit {
+            return Err(Error::InvalidPaymentData);
+        }
+        if let Some(sale_window) = &nft_config.sale_window {
+            check_sale_window(lock_hash, sale_window)?;
+        }
+        check_lifetime_count(&old_payment, &new_payment, buy_count, nft_config.max_packs_per_buyer)?;
+        // packs are bought under the draw rules they will be revealed with
+        if new_payment.version >= PAYMENT_DATA_VERSION_SNAPSHOT && new_payment.config_hash != nft_config.draw_hash {
+            return Err(Error::ConfigMismatch);
+        }
+        // stock of limited-edition series is checked by the sale cell which must join the purchase
+        if nft_config.limited_sale && !check_sale_cell_exists(lock_hash, ownerlock_args, nft_config.series_id)? {
+            return Err(Error::MissingSaleCell);
+        }
+        // packs priced in sUDT credit tokens to the composer's wallet cells instead of ckb, and since
+        // refunds only return capacity, they can't be bought into refundable payment data, while the
+        // amounts paid by every purchase are checked together by `check_required_payments`
+        if nft_config.sudt_price.is_some() {
+            if new_payment.version >= PAYMENT_DATA_VERSION_REFUNDABLE {
+                return Err(Error::InvalidPaymentData);
+            }
+            return Ok(true);
+        }
+        if new_ckb < old_ckb {
             return Err(Error::InsufficientCapacity);
         }
+        check_paid_capacity(&old_payment, &new_payment, new_ckb - old_ckb, minted_count)?;
         return Ok(true);
     }
     return Ok(false);
 }
 
-fn check_reveal_operation(lock_hash: &[u8; 32], old_opt: &DataCapPair, new_opt: &DataCapPair, header_opt: &Option<Header>,
-        nft_count: usize, nft_config: &Vec<(Blake160, u8)>) -> Result<bool, Error> {
-    let (old_data, _) = old_opt.as_ref().unwrap();
-    let (new_data, _) = new_opt.as_ref().unwrap();
+fn sum_minted_packs(lock_hash: &[u8; 32], nft_config: &NftConfig) -> Result<u32, Error> {
+    let mut minted_count = 0u32;
+    for (_, sealed_pack) in collect_minted_sealed_packs(lock_hash)?.iter() {
+        if sealed_pack.series_id != nft_config.series_id {
+            return Err(Error::SeriesMismatch);
+        }
+        // sealed packs are minted under the draw rules they will be revealed with
+        if sealed_pack.draw_hash != nft_config.draw_hash {
+            return Err(Error::ConfigMismatch);
+        }
+        minted_count = minted_count
+            .checked_add(sealed_pack.count)
+            .ok_or(Error::PackCounterOverflow)?;
+    }
+    return Ok(minted_count);
+}
 
-    if old_data.len() == 1 && new_data.len() == 1 && new_data[0] == 0u8 {
-        if header_opt.is_none() {
-            return Err(Error::MissingPaymentHeader);
+// purchases pay ckb, or the sUDT of the type hash, into the wallet cells of a lock hash
+type PaymentKey = (Blake256, Option<Blake256>);
+
+// a wallet can't tell which purchase an inflow came from, so what every purchase in the transaction owes
+// to a lock hash, be it the composer's wallet or a co-publishing recipient, is summed up and checked once
+fn check_required_payments() -> Result<(), Error> {
+    for ((lock_hash, sudt_type_hash_opt), payment) in collect_required_payments()?.iter() {
+        match sudt_type_hash_opt {
+            Some(sudt_type_hash) => {
+                let old_amount = sum_sudt_amount(lock_hash, sudt_type_hash, Source::Input)?;
+                let new_amount = sum_sudt_amount(lock_hash, sudt_type_hash, Source::Output)?;
+                if new_amount < old_amount || new_amount - old_amount < *payment {
+                    return Err(Error::InsufficientSUDTAmount);
+                }
+            },
+            None => {
+                let old_ckb = sum_lock_capacity(lock_hash, Source::Input)? as u128;
+                let new_ckb = sum_lock_capacity(lock_hash, Source::Output)? as u128;
+                if new_ckb < old_ckb || new_ckb - old_ckb < *payment {
+                    return Err(Error::InsufficientCapacity);
+                }
+            }
         }
+    }
+    return Ok(());
+}
 
-        let mut revealed_data = vec![];
-        // filter output cell that filled with NFT type_script instanced by composer's ownerlock hash
-        for i in 0.. {
-            let type_opt = match load_cell_type(i, Source::Output) {
-                Ok(value) => value,
-                Err(SysError::IndexOutOfBound) => break,
-                Err(err) => return Err(Error::from(err))
-            };
-            if let Some(type_) = type_opt {
-                if type_.args().raw_data()[..] == lock_hash[..] {
-                    let output_data = load_cell_data(i, Source::Output)?;
-                    revealed_data = parse_nft_collection(&output_data)?;
-                    break;
+fn collect_required_payments() -> Result<Vec<(PaymentKey, u128)>, Error> {
+    let script = load_script()?;
+    let mut required_payments: Vec<(PaymentKey, u128)> = vec![];
+    for i in 0.. {
+        let type_opt = match load_cell_type(i, Source::Output) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        match type_opt {
+            Some(type_script) => if type_script.code_hash().raw_data()[..] != script.code_hash().raw_data()[..]
+                || type_script.hash_type() != script.hash_type()
+                || type_script.args().raw_data().len() != size_of::<Blake160>() {
+                continue;
+            },
+            None => continue
+        }
+        // every payment cell is checked by its own script group, here only the purchased amount matters
+        let lock_hash = load_cell_lock_hash(i, Source::Output)?;
+        let type_hash = load_cell_type_hash(i, Source::Output)?.unwrap();
+        let input_index = match find_payment_input_index(&lock_hash, &type_hash)? {
+            Some(value) => value,
+            None => continue
+        };
+        let old_payment = parse_payment_data(&load_cell_data(input_index, Source::Input)?)?;
+        let new_payment = parse_payment_data(&load_cell_data(i, Source::Output)?)?;
+        let series_id = get_payment_series(&old_payment, &new_payment)?;
+        if new_payment.count <= old_payment.count && collect_minted_sealed_packs(&lock_hash)?.is_empty() {
+            continue;
+        }
+        let nft_configs_data = load_nft_configs(&lock_hash)?;
+        let nft_config = match get_config_snapshot(&old_payment) {
+            Some(config_hash) => find_nft_params_by_hash(&nft_configs_data, &config_hash)?,
+            None => find_nft_params(&nft_configs_data, series_id)?
+        };
+        let minted_count = sum_minted_packs(&lock_hash, &nft_config)?;
+        let buy_count = new_payment.count
+            .checked_sub(old_payment.count)
+            .ok_or(Error::PackCounterUnderflow)?
+            .checked_add(minted_count)
+            .ok_or(Error::PackCounterOverflow)?;
+        if let Some((sudt_type_hash, sudt_price)) = nft_config.sudt_price {
+            let payment = (buy_count as u128)
+                .checked_mul(sudt_price)
+                .ok_or(Error::PackCounterOverflow)?;
+            add_required_payment(&mut required_payments, (lock_hash, Some(sudt_type_hash)), payment)?;
+            continue;
+        }
+        let payment = (buy_count as u64)
+            .checked_mul(get_ckb_price_perpack(input_index, &nft_config)?)
+            .ok_or(Error::PackCounterOverflow)?;
+        match &nft_config.revenue_split {
+            Some(recipients) => for (recipient_lock_hash, share_payment) in split_payment(recipients, payment).into_iter() {
+                add_required_payment(&mut required_payments, (recipient_lock_hash, None), share_payment as u128)?;
+            },
+            None => add_required_payment(&mut required_payments, (lock_hash, None), payment as u128)?
+        }
+    }
+    return Ok(required_payments);
+}
+
+fn add_required_payment(required_payments: &mut Vec<(PaymentKey, u128)>, key: PaymentKey, payment: u128) -> Result<(), Error> {
+    match required_payments.iter_mut().find(|(required_key, _)| *required_key == key) {
+        Some((_, required)) => {
+            *required = required
+                .checked_add(payment)
+                .ok_or(Error::PackCounterOverflow)?;
+        },
+        None => required_payments.push((key, payment))
+    }
+    return Ok(());
+}
+
+// shares are floored, and the shannons left over by the flooring go to the first recipient
+fn split_payment(recipients: &Vec<(Blake256, u16)>, payment: u64) -> Vec<(Blake256, u64)> {
+    let mut share_payments = recipients
+        .iter()
+        .map(|(lock_hash, share)| {
+            (*lock_hash, (payment as u128 * *share as u128 / REVENUE_SHARE_TOTAL as u128) as u64)
+        })
+        .collect::<Vec<_>>();
+    let shared = share_payments.iter().map(|(_, share_payment)| *share_payment).sum::<u64>();
+    if let Some((_, first_payment)) = share_payments.first_mut() {
+        *first_payment += payment - shared;
+    }
+    return share_payments;
+}
+
+fn sum_lock_capacity(lock_hash: &Blake256, source: Source) -> Result<u64, Error> {
+    let mut capacity = 0u64;
+    for i in 0.. {
+        let cell_lock_hash = match load_cell_lock_hash(i, source) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if cell_lock_hash[..] != lock_hash[..] {
+            continue;
+        }
+        capacity = capacity
+            .checked_add(load_cell_capacity(i, source)?)
+            .ok_or(Error::InsufficientCapacity)?;
+    }
+    return Ok(capacity);
+}
+
+// refundable payment data records every ckb paid into the payment cell since the last reveal, except
+// for packs minted into sealed pack cells which stay with their holders
+fn check_paid_capacity(old_payment: &PaymentData, new_payment: &PaymentData, paid_capacity: u64,
+        minted_count: u32) -> Result<(), Error> {
+    if new_payment.version < PAYMENT_DATA_VERSION_REFUNDABLE {
+        return Ok(());
+    }
+    let paid_capacity = if minted_count > 0 { 0 } else { paid_capacity };
+    let paid = old_payment.paid
+        .checked_add(paid_capacity)
+        .ok_or(Error::InvalidPaymentData)?;
+    if new_payment.paid != paid {
+        return Err(Error::InvalidPaymentData);
+    }
+    return Ok(());
+}
+
+// whitelisted buyers put a merkle proof into the input_type of their payment input witness:
+// leaf_lock_hash(blake256) | [sibling(blake256)]...
+// where the leaf is the digest of a lock hash they prove to own by unlocking a cell of it in the transaction
+fn get_ckb_price_perpack(input_index: usize, nft_config: &NftConfig) -> Result<u64, Error> {
+    let (merkle_root, presale_price) = match &nft_config.whitelist {
+        Some(value) => value,
+        None => return Ok(nft_config.ckb_price_perpack)
+    };
+    let proof = match load_payment_witness_proof(input_index)? {
+        Some(value) => value,
+        None => return Ok(nft_config.ckb_price_perpack)
+    };
+    if proof.is_empty() || proof.len() % size_of::<Blake256>() != 0 {
+        return Err(Error::InvalidWhitelistProof);
+    }
+    let mut sf = StreamFetcher{ index: 0, stream: &proof };
+    let leaf_lock_hash = sf.get_blake256()?;
+    if !check_lock_in_inputs(&leaf_lock_hash)? {
+        return Err(Error::InvalidWhitelistProof);
+    }
+    // siblings are hashed in sorted pairs, so the proof doesn't need to tell left from right
+    let mut node = digest(&leaf_lock_hash.to_vec());
+    while sf.remaining() > 0 {
+        let sibling = sf.get_blake256()?;
+        let mut pair = vec![];
+        if node <= sibling {
+            pair.extend_from_slice(&node);
+            pair.extend_from_slice(&sibling);
+        } else {
+            pair.extend_from_slice(&sibling);
+            pair.extend_from_slice(&node);
+        }
+        node = digest(&pair);
+    }
+    if node[..] != merkle_root[..] {
+        return Err(Error::InvalidWhitelistProof);
+    }
+    return Ok(*presale_price);
+}
+
+fn load_payment_witness_proof(input_index: usize) -> Result<Option<Vec<u8>>, Error> {
+    let witness_args = match load_witness_args(input_index, Source::Input) {
+        Ok(value) => value,
+        Err(SysError::IndexOutOfBound) => return Ok(None),
+        Err(err) => return Err(Error::from(err))
+    };
+    return Ok(witness_args.input_type().to_opt().map(|proof| proof.raw_data().to_vec()));
+}
+
+fn check_lock_in_inputs(lock_hash: &Blake256) -> Result<bool, Error> {
+    for i in 0.. {
+        match load_cell_lock_hash(i, Source::Input) {
+            Ok(value) => if value[..] == lock_hash[..] {
+                return Ok(true);
+            },
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        }
+    }
+    return Ok(false);
+}
+
+// lifetime counter sums up every pack the buyer has bought, which the composer can cap, and since a buyer
+// could always create another payment cell, capped packs are only sold into the one the composer registered
+fn check_lifetime_count(old_payment: &PaymentData, new_payment: &PaymentData, buy_count: u32,
+        max_packs_per_buyer: Option<u32>) -> Result<(), Error> {
+    if new_payment.version < PAYMENT_DATA_VERSION_LIFETIME {
+        if max_packs_per_buyer.is_some() {
+            return Err(Error::InvalidPaymentData);
+        }
+        return Ok(());
+    }
+    if max_packs_per_buyer.is_some() && !new_payment.registered {
+        return Err(Error::UnregisteredPaymentCell);
+    }
+    let lifetime_count = old_payment.lifetime_count
+        .checked_add(buy_count)
+        .ok_or(Error::PackCounterOverflow)?;
+    if new_payment.lifetime_count != lifetime_count {
+        return Err(Error::InvalidPaymentData);
+    }
+    if let Some(max_packs) = max_packs_per_buyer {
+        if lifetime_count > max_packs {
+            return Err(Error::PurchaseLimitExceeded);
+        }
+    }
+    return Ok(());
+}
+
+// buyer can get back what has been paid for unrevealed packs `REFUND_DELAY_BLOCKS` blocks after their
+// purchase, in case the composer never lets them be revealed
+const REFUND_DELAY_BLOCKS: u64 = 100_000;
+const SINCE_ABSOLUTE_BLOCK_NUMBER_FLAG: u64 = 0;
+const SINCE_FLAGS_MASK: u64 = 0xff00_0000_0000_0000;
+
+fn load_payment_since(lock_hash: &[u8; 32]) -> Result<u64, Error> {
+    for i in 0.. {
+        let input_lock_hash = match load_cell_lock_hash(i, Source::GroupInput) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if input_lock_hash[..] == lock_hash[..] {
+            return Ok(load_input_since(i, Source::GroupInput)?);
+        }
+    }
+    return Err(Error::MissingCells);
+}
+
+// purchases can't be committed before the absolute block number set in the payment input's since, which
+// has to be in the sale window, and the window is only checked at purchase since it stays editable
+fn check_sale_window(lock_hash: &[u8; 32], sale_window: &SaleWindow) -> Result<(), Error> {
+    if sale_window.paused {
+        return Err(Error::SalePaused);
+    }
+    let since = load_payment_since(lock_hash)?;
+    if since & SINCE_FLAGS_MASK != SINCE_ABSOLUTE_BLOCK_NUMBER_FLAG
+        || since < sale_window.start_block
+        || since > sale_window.end_block {
+        return Err(Error::SaleWindowClosed);
+    }
+    return Ok(());
+}
+
+fn check_refund_operation(lock_hash: &[u8; 32], old_opt: &DataCapPair, new_opt: &DataCapPair, nft_configs_data: &Vec<Vec<u8>>,
+        header_opt: &Option<Header>) -> Result<bool, Error> {
+    let (_, old_ckb) = old_opt.as_ref().unwrap();
+    let (_, new_ckb) = new_opt.as_ref().unwrap();
+
+    // only refunds can take capacity out of the payment cell in guest mode
+    if new_ckb >= old_ckb {
+        return Ok(false);
+    }
+    let (old_payment, new_payment) = parse_payment_pair(old_opt, new_opt)?;
+    if old_payment.count == 0 || new_payment.count != 0 || new_payment.paid != 0 {
+        return Err(Error::InsufficientCapacity);
+    }
+    // an emptied counter leaves no partial reveal behind, just like revealing the last pack
+    if new_payment.packs_since_hit != old_payment.packs_since_hit
+        || new_payment.lifetime_count != old_payment.lifetime_count
+        || new_payment.config_hash != old_payment.config_hash
+        || new_payment.draw_offset != 0
+        || new_payment.anchor_block != 0 {
+        return Err(Error::InvalidPaymentData);
+    }
+    if old_ckb - new_ckb > old_payment.paid {
+        return Err(Error::RefundOutOfBound);
+    }
+
+    // a buyer able to reveal would only refund the packs they know to draw badly, so the packs must have
+    // been bought under draw rules which the config cell of their series no longer has
+    if old_payment.version < PAYMENT_DATA_VERSION_SNAPSHOT
+        || find_nft_params(nft_configs_data, old_payment.series_id)?.draw_hash == old_payment.config_hash {
+        return Err(Error::RefundNotMatured);
+    }
+
+    // payment cell must be consumed with an absolute block number since, counted from the purchase block
+    // which neither later purchases nor the composer's withdrawals move
+    let purchase_block = get_purchase_anchor(&old_payment, header_opt)?;
+    let since = load_payment_since(lock_hash)?;
+    if since & SINCE_FLAGS_MASK != SINCE_ABSOLUTE_BLOCK_NUMBER_FLAG
+        || since < purchase_block.saturating_add(REFUND_DELAY_BLOCKS) {
+        return Err(Error::RefundNotMatured);
+    }
+    return Ok(true);
+}
+
+// sale cell shares composer's ownerlock and holds the remaining stock of the series
+fn check_sale_cell_exists(lock_hash: &[u8; 32], ownerlock_args: &Bytes, series_id: u32) -> Result<bool, Error> {
+    let code_hash = load_script()?.code_hash();
+    let composer_pubkey_hash = get_ownerlock_pubkey_hash(ownerlock_args);
+    for i in 0.. {
+        let cell = match load_cell(i, Source::Input) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if let Some(type_) = cell.type_().to_opt() {
+            if type_.code_hash().raw_data()[..] != code_hash.raw_data()[..] || type_.args().raw_data().len() != SALE_ARGS_SIZE {
+                continue;
+            }
+            let sale_args = SaleArgs::decode(&type_.args().raw_data())?;
+            if sale_args.composer_pubkey_hash[..] == composer_pubkey_hash[..]
+                && sale_args.series_id == series_id
+                && load_cell_lock_hash(i, Source::Input)?[..] == lock_hash[..] {
+                return Ok(true);
+            }
+        }
+    }
+    return Ok(false);
+}
+
+fn check_sale_operation(sale_args: &Bytes) -> Result<(), Error> {
+    // composer can freely create, restock or close the sale
+    let sale_args = SaleArgs::decode(sale_args)?;
+    if let Ok(pubkey_hash) = get_signature_pubkey_hash() {
+        if pubkey_hash[..] == sale_args.composer_pubkey_hash[..] {
+            return Ok(());
+        }
+    }
+
+    // anyone else can only pass one sale cell through a purchase transaction
+    let load_sale_cell = |source: Source| -> Result<(u32, u64, [u8; 32]), Error> {
+        if let Err(SysError::IndexOutOfBound) = load_cell_capacity(1, source) {
+            let stock = decode_sale_stock(&load_cell_data(0, source)?)?;
+            let capacity = load_cell_capacity(0, source)?;
+            let lock_hash = load_cell_lock_hash(0, source)?;
+            return Ok((stock, capacity, lock_hash));
+        }
+        return Err(Error::DumplicateInputCell);
+    };
+    let (old_stock, old_capacity, lock_hash) = load_sale_cell(Source::GroupInput)?;
+    let (new_stock, new_capacity, new_lock_hash) = load_sale_cell(Source::GroupOutput)?;
+    if lock_hash[..] != new_lock_hash[..] {
+        return Err(Error::InvalidSaleData);
+    }
+    if new_capacity < old_capacity {
+        return Err(Error::InsufficientCapacity);
+    }
+
+    // stock must be decreased by exactly the packs of this series bought in the transaction
+    let mut sold = 0u32;
+    let code_hash = load_script()?.code_hash();
+    let series_id = sale_args.series_id;
+    for i in 0.. {
+        let cell = match load_cell(i, Source::Output) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        let type_ = match cell.type_().to_opt() {
+            Some(value) => value,
+            None => continue
+        };
+        if type_.code_hash().raw_data()[..] != code_hash.raw_data()[..]
+            || type_.args().raw_data().len() != size_of::<Blake160>()
+            || load_cell_lock_hash(i, Source::Output)?[..] != lock_hash[..] {
+            continue;
+        }
+        let new_payment = parse_payment_data(&load_cell_data(i, Source::Output)?)?;
+        let type_hash = load_cell_type_hash(i, Source::Output)?.unwrap();
+        let old_count = match find_payment_input(&lock_hash, &type_hash)? {
+            Some(old_payment) => old_payment.count,
+            None => 0
+        };
+        if new_payment.count > old_count && new_payment.series_id == series_id {
+            sold = sold
+                .checked_add(new_payment.count - old_count)
+                .ok_or(Error::PackCounterOverflow)?;
+        }
+    }
+    for (_, sealed_pack) in collect_minted_sealed_packs(&lock_hash)?.iter() {
+        if sealed_pack.series_id == series_id {
+            sold = sold
+                .checked_add(sealed_pack.count)
+                .ok_or(Error::PackCounterOverflow)?;
+        }
+    }
+    if old_stock < sold {
+        return Err(Error::SoldOut);
+    }
+    if old_stock - sold != new_stock {
+        return Err(Error::InvalidSaleData);
+    }
+    return Ok(());
+}
+
+// sealed pack cell carries unrevealed packs outside of buyer's payment cell, keyed by the composer's ownerlock hash
+fn load_sealed_packs(ownerlock_hash: &[u8], source: Source) -> Result<Vec<(usize, SealedPack)>, Error> {
+    let code_hash = load_script()?.code_hash();
+    let mut sealed_packs: Vec<(usize, SealedPack)> = vec![];
+    for i in 0.. {
+        let type_opt = match load_cell_type(i, source) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if let Some(type_) = type_opt {
+            if type_.code_hash().raw_data()[..] == code_hash.raw_data()[..] && type_.args().raw_data()[..] == ownerlock_hash[..] {
+                let sealed_pack = SealedPack::decode(&load_cell_data(i, source)?)?;
+                if sealed_packs.iter().any(|(_, pack)| pack.pack_id == sealed_pack.pack_id) {
+                    return Err(Error::InvalidSealedPackData);
                 }
+                sealed_packs.push((i, sealed_pack));
             }
         }
-        if revealed_data.is_empty() {
-            return Err(Error::MissingCells);
+    }
+    return Ok(sealed_packs);
+}
+
+fn collect_minted_sealed_packs(ownerlock_hash: &[u8; 32]) -> Result<Vec<(usize, SealedPack)>, Error> {
+    let input_packs = load_sealed_packs(ownerlock_hash, Source::Input)?;
+    let minted_packs = load_sealed_packs(ownerlock_hash, Source::Output)?
+        .into_iter()
+        .filter(|(_, output_pack)| !input_packs.iter().any(|(_, input_pack)| input_pack.pack_id == output_pack.pack_id))
+        .collect::<Vec<_>>();
+    return Ok(minted_packs);
+}
+
+// sealed packs are only paid by purchases, so wallets spent by any other operation can't mint them
+fn check_no_minted_sealed_packs(ownerlock_hash: &[u8; 32]) -> Result<(), Error> {
+    if !collect_minted_sealed_packs(ownerlock_hash)?.is_empty() {
+        return Err(Error::InvalidSealedPackData);
+    }
+    return Ok(());
+}
+
+fn check_sealed_pack_operation(ownerlock_hash: &Bytes) -> Result<(), Error> {
+    let input_packs = load_sealed_packs(ownerlock_hash, Source::Input)?;
+    let output_packs = load_sealed_packs(ownerlock_hash, Source::Output)?;
+
+    // sealed packs are indivisible, so a transfer only fills the anchor block the first time
+    let mut revealed_packs = vec![];
+    for (index, input_pack) in input_packs.iter() {
+        match output_packs.iter().find(|(_, output_pack)| output_pack.pack_id == input_pack.pack_id) {
+            Some((_, output_pack)) => {
+                let anchor_block = match input_pack.anchor_block {
+                    0 => {
+                        let header = load_header(*index, Source::Input).map_err(|_| Error::MissingPaymentHeader)?;
+                        header.raw().number().unpack()
+                    },
+                    value => value
+                };
+                if output_pack.series_id != input_pack.series_id
+                    || output_pack.count != input_pack.count
+                    || output_pack.anchor_block != anchor_block
+                    || output_pack.draw_hash != input_pack.draw_hash {
+                    return Err(Error::InvalidSealedPackData);
+                }
+            },
+            None => revealed_packs.push((*index, input_pack))
+        }
+    }
+
+    // only one sealed pack can be revealed by a transaction, since it takes the first NFT output
+    if revealed_packs.len() > 1 {
+        return Err(Error::InvalidSealedPackData);
+    }
+    if let Some((index, sealed_pack)) = revealed_packs.first() {
+        check_sealed_pack_reveal(ownerlock_hash, *index, sealed_pack)?;
+    }
+
+    // minted packs take type-id like ids and must be paid by a payment cell of the ownerlock
+    let first_out_point = load_input_out_point(0, Source::Input)?;
+    let mut minted = false;
+    for (index, output_pack) in output_packs.iter() {
+        if input_packs.iter().any(|(_, input_pack)| input_pack.pack_id == output_pack.pack_id) {
+            continue;
+        }
+        let mut pack_id_source = first_out_point.as_slice().to_vec();
+        pack_id_source.append(&mut (*index as u64).to_le_bytes().to_vec());
+        if output_pack.anchor_block != 0 || output_pack.pack_id != digest(&pack_id_source) {
+            return Err(Error::InvalidSealedPackData);
+        }
+        minted = true;
+    }
+    if minted && !check_payment_cell_exists(ownerlock_hash)? {
+        return Err(Error::MissingCells);
+    }
+    return Ok(());
+}
+
+fn check_payment_cell_exists(ownerlock_hash: &[u8]) -> Result<bool, Error> {
+    let code_hash = load_script()?.code_hash();
+    for i in 0.. {
+        let cell = match load_cell(i, Source::Input) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if let Some(type_) = cell.type_().to_opt() {
+            if type_.code_hash().raw_data()[..] == code_hash.raw_data()[..]
+                && type_.args().raw_data().len() == size_of::<Blake160>()
+                && load_cell_lock_hash(i, Source::Input)?[..] == ownerlock_hash[..] {
+                return Ok(true);
+            }
+        }
+    }
+    return Ok(false);
+}
+
+fn check_sealed_pack_reveal(ownerlock_hash: &Bytes, index: usize, sealed_pack: &SealedPack) -> Result<(), Error> {
+    let mut lock_hash = [0u8; 32];
+    lock_hash.copy_from_slice(&ownerlock_hash[..]);
+    // the config can be edited after mint, so the pack is revealed with the draw rules it was minted under
+    let nft_config = find_nft_params_by_hash(&load_nft_configs(&lock_hash)?, &sealed_pack.draw_hash)?;
+
+    // the anchor header is the block the pack was minted in, either untouched since or recorded by a transfer
+    let anchor_header = match sealed_pack.anchor_block {
+        0 => load_header(index, Source::Input).map_err(|_| Error::MissingPaymentHeader)?,
+        anchor_block => load_header_by_number(anchor_block)?.ok_or(Error::MissingPaymentHeader)?
+    };
+    let reveal_header = load_reveal_header(&anchor_header, nft_config.reveal_delay)?;
+
+    let revealed_data = load_revealed_nft(&lock_hash, None, &nft_config)?;
+    let max_count_can_reveal = sealed_pack.count as usize * nft_config.nft_count_perpack as usize;
+    if revealed_data.len() > max_count_can_reveal {
+        return Err(Error::RevealedNFTOutOfBound);
+    }
+    if nft_config.pity_rule.is_some() && revealed_data.len() != max_count_can_reveal {
+        return Err(Error::InvalidRevealNFTData);
+    }
+
+    // sealed packs carry no pity counter, so the rule only counts the packs revealed together
+    let seed = build_lottery_seed(&anchor_header, &reveal_header, &sealed_pack.pack_id)?;
+    verify_revealed_nft(&revealed_data, &nft_config, &seed, 0, 0)?;
+    return Ok(());
+}
+
+fn load_header_by_number(number: u64) -> Result<Option<Header>, Error> {
+    for i in 0.. {
+        let header = match load_header(i, Source::HeaderDep) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        let header_number: u64 = header.raw().number().unpack();
+        if header_number == number {
+            return Ok(Some(header));
+        }
+    }
+    return Ok(None);
+}
+
+fn load_nft_configs(ownerlock_hash: &[u8; 32]) -> Result<Vec<Vec<u8>>, Error> {
+    let code_hash = load_script()?.code_hash();
+    let mut nft_configs_data = vec![];
+    for i in 0.. {
+        let cell = match load_cell(i, Source::CellDep) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        let type_ = match cell.type_().to_opt() {
+            Some(value) => value,
+            None => continue
+        };
+        if type_.code_hash().raw_data()[..] != code_hash.raw_data()[..]
+            || get_ownerlock_pubkey_hash(&cell.lock().args().raw_data())[..] != type_.args().raw_data()[..]
+            || load_cell_lock_hash(i, Source::CellDep)?[..] != ownerlock_hash[..] {
+            continue;
+        }
+        nft_configs_data.push(load_cell_data(i, Source::CellDep)?);
+    }
+    return Ok(nft_configs_data);
+}
+
+fn find_payment_input(lock_hash: &[u8; 32], type_hash: &[u8]) -> Result<Option<PaymentData>, Error> {
+    match find_payment_input_index(lock_hash, type_hash)? {
+        Some(index) => return Ok(Some(parse_payment_data(&load_cell_data(index, Source::Input)?)?)),
+        None => return Ok(None)
+    }
+}
+
+fn find_payment_input_index(lock_hash: &[u8; 32], type_hash: &[u8]) -> Result<Option<usize>, Error> {
+    for i in 0.. {
+        let input_type_hash = match load_cell_type_hash(i, Source::Input) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if let Some(input_type_hash) = input_type_hash {
+            if input_type_hash[..] == type_hash[..] && load_cell_lock_hash(i, Source::Input)?[..] == lock_hash[..] {
+                return Ok(Some(i));
+            }
+        }
+    }
+    return Ok(None);
+}
+
+const SUDT_AMOUNT_SIZE: usize = size_of::<u128>();
+
+fn sum_sudt_amount(lock_hash: &[u8; 32], sudt_type_hash: &Blake256, source: Source) -> Result<u128, Error> {
+    let mut amount = 0u128;
+    for i in 0.. {
+        let cell_lock_hash = match load_cell_lock_hash(i, source) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if cell_lock_hash[..] != lock_hash[..] {
+            continue;
+        }
+        match load_cell_type_hash(i, source)? {
+            Some(type_hash) => if type_hash[..] != sudt_type_hash[..] {
+                continue;
+            },
+            None => continue
+        }
+        let data = load_cell_data(i, source)?;
+        if data.len() < SUDT_AMOUNT_SIZE {
+            return Err(Error::Encoding);
+        }
+        let mut sf = StreamFetcher{ index: 0, stream: &data };
+        amount = amount
+            .checked_add(sf.get_u128()?)
+            .ok_or(Error::Encoding)?;
+    }
+    return Ok(amount);
+}
+
+// block of the purchase that unrevealed packs are drawn from, which is the block holding the payment
+// cell until a transaction recreates it with packs left and records the block in the payment data
+fn get_purchase_anchor(old_payment: &PaymentData, header_opt: &Option<Header>) -> Result<u64, Error> {
+    if old_payment.count == 0 {
+        return Ok(0);
+    }
+    if old_payment.anchor_block != 0 {
+        return Ok(old_payment.anchor_block);
+    }
+    match header_opt {
+        Some(header) => Ok(header.raw().number().unpack()),
+        None => Err(Error::MissingPaymentHeader)
+    }
+}
+
+fn check_reveal_operation(lock_hash: &[u8; 32], payment_args: &Bytes, old_opt: &DataCapPair, new_opt: &DataCapPair,
+        header_opt: &Option<Header>, nft_config: &NftConfig) -> Result<bool, Error> {
+    let (old_payment, new_payment) = parse_payment_pair(old_opt, new_opt)?;
+
+    // the counter can only be decreased by revealing, which takes the whole counter unless the payment data
+    // is able to record the draws already made
+    if new_payment.count < old_payment.count && new_payment.count != 0 && new_payment.version < PAYMENT_DATA_VERSION_PARTIAL {
+        return Err(Error::PackCounterUnderflow);
+    }
+
+    if new_payment.count == 0 || new_payment.count < old_payment.count {
+        // what has been paid is no longer refundable once the packs are revealed, while the lifetime
+        // counter is kept for good
+        if new_payment.paid != 0
+            || new_payment.lifetime_count != old_payment.lifetime_count
+            || new_payment.config_hash != old_payment.config_hash {
+            return Err(Error::InvalidPaymentData);
+        }
+        if new_payment.count != 0 && new_payment.series_id != old_payment.series_id {
+            return Err(Error::SeriesMismatch);
         }
+        // packs are drawn from the block they were bought in, unless the payment cell has moved since
+        // and recorded it
+        let anchor_header_opt = match old_payment.anchor_block {
+            0 => header_opt.clone(),
+            anchor_block => load_header_by_number(anchor_block)?
+        };
+        if anchor_header_opt.is_none() {
+            return Err(Error::MissingPaymentHeader);
+        }
+        let purchase_header = anchor_header_opt.as_ref().unwrap();
+        let purchase_number: u64 = purchase_header.raw().number().unpack();
+        let reveal_header = load_reveal_header(purchase_header, nft_config.reveal_delay)?;
+
+        let revealed_data = load_revealed_nft(lock_hash, Some(payment_args), nft_config)?;
 
-        let buy_count = old_data[0] as usize;
-        let max_count_can_reveal = buy_count * nft_count;
+        let reveal_count = (old_payment.count - new_payment.count) as usize;
+        let max_count_can_reveal = reveal_count * nft_config.nft_count_perpack as usize;
 
         if revealed_data.len() > max_count_can_reveal {
             return Err(Error::RevealedNFTOutOfBound);
         }
-
-        if !verify_revealed_nft(&revealed_data, &nft_config, header_opt.as_ref().unwrap()) {
+        // pity rules are counted by pack, so every purchased pack must be revealed in full
+        if nft_config.pity_rule.is_some() && revealed_data.len() != max_count_can_reveal {
             return Err(Error::InvalidRevealNFTData);
         }
+
+        // the remaining packs continue drawing right after the revealed ones, whether or not they were all drawn
+        let (draw_offset, anchor_block) = match new_payment.count {
+            0 => (0, 0),
+            _ => {
+                let draw_offset = (old_payment.draw_offset as usize)
+                    .checked_add(max_count_can_reveal)
+                    .and_then(|value| value.try_into().ok())
+                    .ok_or(Error::InvalidPaymentData)?;
+                (draw_offset, purchase_number)
+            }
+        };
+        if new_payment.draw_offset != draw_offset || new_payment.anchor_block != anchor_block {
+            return Err(Error::InvalidPaymentData);
+        }
+
+        let seed = build_lottery_seed(purchase_header, &reveal_header, &load_script_hash()?)?;
+        let packs_since_hit = verify_revealed_nft(
+            &revealed_data, &nft_config, &seed, old_payment.packs_since_hit, old_payment.draw_offset as usize
+        )?;
+        if new_payment.packs_since_hit != packs_since_hit {
+            return Err(Error::InvalidPaymentData);
+        }
         return Ok(true);
     }
     return Ok(false);
 }
 
-fn verify_revealed_nft(revealed_data: &Vec<Blake160>, config_data: &Vec<(Blake160, u8)>, header: &Header) -> bool {
+// buyers revealing in the same transaction share the nft type script, so each of them only takes the
+// output locked by their own pubkey hash, whose lock args start with it
+fn load_revealed_nft(lock_hash: &[u8; 32], owner_opt: Option<&Bytes>, nft_config: &NftConfig) -> Result<Vec<Blake160>, Error> {
+    // filter output cell that filled with composer's NFT type_script instanced by composer's ownerlock hash
+    for i in 0.. {
+        let type_opt = match load_cell_type(i, Source::Output) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if let Some(type_) = type_opt {
+            let hash_type: u8 = type_.hash_type().into();
+            if type_.args().raw_data()[..] == lock_hash[..]
+                && type_.code_hash().raw_data()[..] == nft_config.nft_code_hash[..]
+                && hash_type == nft_config.nft_hash_type {
+                if let Some(owner) = owner_opt {
+                    let lock_args = load_cell_lock(i, Source::Output)?.args().raw_data();
+                    if lock_args.len() < owner.len() || lock_args[..owner.len()] != owner[..] {
+                        continue;
+                    }
+                }
+                let output_data = load_cell_data(i, Source::Output)?;
+                let input_data = match load_collection_input(i)? {
+                    Some(value) => value,
+                    None => return parse_nft_collection(&output_data)
+                };
+                // revealed nfts are appended to the collection, which can't be changed otherwise
+                if output_data.len() <= input_data.len() || output_data[..input_data.len()] != input_data[..] {
+                    return Err(Error::InvalidRevealNFTData);
+                }
+                return parse_nft_collection(&output_data[input_data.len()..].to_vec());
+            }
+        }
+    }
+    return Err(Error::MissingCells);
+}
+
+// find the input collection cell that the output one grows from, which keeps both lock and type
+fn load_collection_input(output_index: usize) -> Result<Option<Vec<u8>>, Error> {
+    let type_hash = load_cell_type_hash(output_index, Source::Output)?;
+    let lock_hash = load_cell_lock_hash(output_index, Source::Output)?;
+    for i in 0.. {
+        let input_type_hash = match load_cell_type_hash(i, Source::Input) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        if input_type_hash == type_hash && load_cell_lock_hash(i, Source::Input)?[..] == lock_hash[..] {
+            if load_cell_capacity(output_index, Source::Output)? < load_cell_capacity(i, Source::Input)? {
+                return Err(Error::InvalidRevealNFTData);
+            }
+            let input_data = load_cell_data(i, Source::Input)?;
+            if input_data.len() % size_of::<Blake160>() != 0 {
+                return Err(Error::InvalidNFTData);
+            }
+            return Ok(Some(input_data));
+        }
+    }
+    return Ok(None);
+}
+
+// the reveal header is pinned to exactly `reveal_delay` blocks after the purchase block, so the buyer
+// can neither know it while purchasing nor pick a favourable one among several later headers
+fn load_reveal_header(purchase_header: &Header, reveal_delay: u64) -> Result<Header, Error> {
+    let purchase_number: u64 = purchase_header.raw().number().unpack();
+    let reveal_number = purchase_number
+        .checked_add(reveal_delay)
+        .ok_or(Error::MissingRevealHeader)?;
+    for i in 0.. {
+        let header = match load_header(i, Source::HeaderDep) {
+            Ok(value) => value,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        };
+        let number: u64 = header.raw().number().unpack();
+        if number == reveal_number {
+            return Ok(header);
+        }
+    }
+    return Err(Error::MissingRevealHeader);
+}
+
+// lottery seed combines both headers with a salt fixed at purchase time, which is the payment script hash
+// committing to the buyer's pubkey hash, or the id of a sealed pack, so it can't be swapped for a luckier one
+fn build_lottery_seed(purchase_header: &Header, reveal_header: &Header, salt: &[u8; 32]) -> Result<[u8; 32], Error> {
+    let mut seed = vec![];
+    seed.append(&mut digest(&purchase_header.as_slice().to_vec()).to_vec());
+    seed.append(&mut digest(&reveal_header.as_slice().to_vec()).to_vec());
+    seed.append(&mut salt.to_vec());
+    return Ok(digest(&seed));
+}
+
+// every revealed nft consumes `LOTTERY_SIZE` bytes of entropy, so that weights can go far below 1/256
+const LOTTERY_SIZE: usize = size_of::<u64>();
+
+fn draw_nft(nfts: &Vec<(Blake160, u32)>, candidates: &Vec<usize>, lottery: u64) -> usize {
+    let total_weight = candidates
+        .iter()
+        .map(|&i| nfts[i].1 as u64)
+        .sum::<u64>();
+    let lottery = lottery % total_weight;
+    let mut weight_sum = 0u64;
+    for &i in candidates.iter() {
+        weight_sum += nfts[i].1 as u64;
+        if lottery < weight_sum {
+            return i;
+        }
+    }
+    unreachable!()
+}
+
+// check revealed nft data whether matches composer's NFT config data, and return the pity counter
+// that results from revealing them on top of `packs_since_hit`, drawing lotteries from `draw_offset` on
+fn verify_revealed_nft(revealed_data: &Vec<Blake160>, nft_config: &NftConfig, seed: &[u8; 32],
+        mut packs_since_hit: u32, draw_offset: usize) -> Result<u32, Error> {
     // build lottery array
-    let mut lotteries = digest(&header.as_slice().to_vec()).to_vec();
+    let mut lotteries = seed.to_vec();
     debug!("lotteries = {:?}", lotteries);
 
-    // check revealed nft data whether matches composer's NFT config data
-    for i in 0..revealed_data.len() {
-        let reveal_nft = revealed_data[i];
-        let expect_nft = {
-            if i >= lotteries.len() {
-                let next_hash = digest(&lotteries.to_vec());
-                lotteries.append(&mut next_hash.to_vec());
-                debug!("next lotteries = {:?}", lotteries);
-            }
-            let lottery = lotteries[i];
-            let mut nft_data: Option<Blake160> = None;
-            for &(nft, rate) in config_data.iter() {
-                if lottery < rate {
-                    nft_data = Some(nft);
-                    break;
+    let all_nfts = (0..nft_config.nfts.len()).collect::<Vec<usize>>();
+    let pack_size = nft_config.nft_count_perpack as usize;
+    let mut pack_hit = false;
+    for (i, revealed_nft) in revealed_data.iter().enumerate() {
+        let i = draw_offset + i;
+        while (i + 1) * LOTTERY_SIZE > lotteries.len() {
+            let next_hash = digest(&lotteries.to_vec());
+            lotteries.append(&mut next_hash.to_vec());
+            debug!("next lotteries = {:?}", lotteries);
+        }
+        let mut sf = StreamFetcher{ index: i * LOTTERY_SIZE, stream: &lotteries };
+        let lottery = sf.get_u64()?;
+        let expect_nft = match &nft_config.pity_rule {
+            Some(pity_rule) => {
+                let last_slot = (i + 1) % pack_size == 0;
+                // the last card of a pack is drawn from the tier when its guarantee runs out
+                let index = if last_slot && !pack_hit && packs_since_hit.saturating_add(1) >= pity_rule.interval {
+                    draw_nft(&nft_config.nfts, &pity_rule.nft_indices, lottery)
+                } else {
+                    draw_nft(&nft_config.nfts, &all_nfts, lottery)
+                };
+                pack_hit = pack_hit || pity_rule.nft_indices.contains(&index);
+                if last_slot {
+                    packs_since_hit = if pack_hit { 0 } else { packs_since_hit.saturating_add(1) };
+                    pack_hit = false;
                 }
-            }
-            if nft_data.is_none() {
-                nft_data = Some(config_data[config_data.len() - 1].0);
-            }
-            nft_data.unwrap()
+                nft_config.nfts[index].0
+            },
+            None => nft_config.nfts[draw_nft(&nft_config.nfts, &all_nfts, lottery)].0
         };
-        if reveal_nft[..] != expect_nft[..] {
-            return false
+        if revealed_nft[..] != expect_nft[..] {
+            return Err(Error::InvalidRevealNFTData);
         }
     }
-    return true;
+    return Ok(packs_since_hit);
 }
 
-fn parse_nft_params(data: &Vec<u8>) -> Result<(u64, u8, Vec<(Blake160, u8)>), Error> {
-    let const_size = size_of::<u64>() + size_of::<u8>();
-    let single_nft_size = size_of::<Blake160>() + size_of::<u8>();
-    let nft_count = (data.len() - const_size) / single_nft_size;
-    if data.len() < const_size + single_nft_size || nft_count < 1 {
-        return Err(Error::InvalidNFTData);
+// composer config decoded by the shared codec, along with the digest of its draw rules
+struct NftConfig {
+    draw_hash: Blake256,
+    params: ConfigParams
+}
+
+impl Deref for NftConfig {
+    type Target = ConfigParams;
+
+    fn deref(&self) -> &ConfigParams {
+        &self.params
     }
-    let mut sf = StreamFetcher{ index: 0, stream: &data };
-    let ckb_unit_price = sf.get_u64();
-    let nft_unit_count = sf.get_u8();
-    let mut nft_config = vec![];
-    for _ in 0..nft_count {
-        let nft = sf.get_blake160();
-        let nft_rate = sf.get_u8();
-        nft_config.push((nft, nft_rate));
-    }
-    // nft_config must be asc ordered by rate
-    let mut last_rate = 0u8;
-    for &(_, rate) in nft_config.iter() {
-        if last_rate <= rate {
-            last_rate = rate;
-        } else {
-            return Err(Error::InvalidNFTData);
+}
+
+fn parse_nft_params(data: &Vec<u8>) -> Result<NftConfig, Error> {
+    let params = ConfigParams::decode(data)?;
+    return Ok(NftConfig {
+        draw_hash: digest(&params.encode_draw_rules()),
+        params
+    });
+}
+
+// unrevealed packs bought with a snapshot can only be revealed with the very draw rules they were bought under
+fn get_config_snapshot(old_payment: &PaymentData) -> Option<Blake256> {
+    if old_payment.version >= PAYMENT_DATA_VERSION_SNAPSHOT && old_payment.count > 0 {
+        return Some(old_payment.config_hash);
+    }
+    return None;
+}
+
+fn find_nft_params_by_hash(nft_configs_data: &Vec<Vec<u8>>, config_hash: &Blake256) -> Result<NftConfig, Error> {
+    for nft_config_data in nft_configs_data.iter() {
+        let nft_config = parse_nft_params(nft_config_data)?;
+        if nft_config.draw_hash[..] == config_hash[..] {
+            return Ok(nft_config);
+        }
+    }
+    return Err(Error::ConfigMismatch);
+}
+
+fn find_nft_params(nft_configs_data: &Vec<Vec<u8>>, series_id: u32) -> Result<NftConfig, Error> {
+    for nft_config_data in nft_configs_data.iter() {
+        let nft_config = parse_nft_params(nft_config_data)?;
+        if nft_config.series_id == series_id {
+            return Ok(nft_config);
         }
     }
-    return Ok((ckb_unit_price, nft_unit_count, nft_config));
+    return Err(Error::MissingCells);
 }
 
 fn parse_nft_collection(data: &Vec<u8>) -> Result<Vec<Blake160>, Error> {
-    if data.is_empty() || data.len() % size_of::<Blake160>() != 0 {
+    let nft_collection = decode_nft_collection(data)?;
+    if nft_collection.is_empty() {
         return Err(Error::InvalidNFTData);
     }
-    let size = data.len() / size_of::<Blake160>();
-    let mut sf = StreamFetcher{ index: 0, stream: &data };
-    let mut nft_collection = vec![];
-    for _i in 0..size {
-        let nft = sf.get_blake160();
-        nft_collection.push(nft);
-    }
     return Ok(nft_collection);
 }
+
+fn parse_payment_data(data: &Vec<u8>) -> Result<PaymentData, Error> {
+    return Ok(PaymentData::decode(data)?);
+}
+
+fn parse_payment_pair(old_opt: &DataCapPair, new_opt: &DataCapPair) -> Result<(PaymentData, PaymentData), Error> {
+    let (old_data, _) = old_opt.as_ref().unwrap();
+    let (new_data, _) = new_opt.as_ref().unwrap();
+    let old_payment = parse_payment_data(old_data)?;
+    let new_payment = parse_payment_data(new_data)?;
+    // payment data can be upgraded to a later layout, but never downgraded, nor registered after creation
+    if new_payment.version < old_payment.version || new_payment.registered != old_payment.registered {
+        return Err(Error::InvalidPaymentData);
+    }
+    return Ok((old_payment, new_payment));
+}
+
+// purchases name the series in the output data, and unrevealed packs must all belong to one series
+fn get_payment_series(old_payment: &PaymentData, new_payment: &PaymentData) -> Result<u32, Error> {
+    if new_payment.count > old_payment.count || old_payment.count == 0 {
+        if old_payment.count > 0 && old_payment.series_id != new_payment.series_id {
+            return Err(Error::SeriesMismatch);
+        }
+        return Ok(new_payment.series_id);
+    }
+    return Ok(old_payment.series_id);
+}
@@ -3,10 +3,7 @@ use core::result::Result;
 
 // Import heap related library from `alloc`
 // https://doc.rust-lang.org/alloc/index.html
-use alloc::{
-    vec,
-    vec::Vec
-};
+use alloc::vec;
 
 // Import CKB syscalls and structures
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
@@ -29,9 +26,21 @@ pub fn main() -> Result<(), Error> {
         return Ok(());
     }
 
+    // capacity of cells typed by the delegate script (if any) is checked by that script instead
+    let delegate_code_hash = get_delegate_code_hash()?;
+
     // output ckb must be greator or equal than input ckb
-    let old_ckb = QueryIter::new(load_cell_capacity, Source::GroupInput)
-        .collect::<Vec<u64>>()
+    let mut in_capacities = vec![];
+    for i in 0.. {
+        match load_cell_capacity(i, Source::GroupInput) {
+            Ok(capacity) => if !check_delegated_cell(&delegate_code_hash, i, Source::GroupInput)? {
+                in_capacities.push(capacity);
+            },
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(Error::from(err))
+        }
+    }
+    let old_ckb = in_capacities
         .into_iter()
         .sum::<u64>();
     let script_hash = load_script_hash()?;
@@ -39,8 +48,10 @@ pub fn main() -> Result<(), Error> {
     for i in 0.. {
         match load_cell_lock_hash(i, Source::Output) {
             Ok(lock_hash) => if script_hash[..] == lock_hash[..] {
-                let capacity = load_cell_capacity(i, Source::Output)?;
-                out_capacities.push(capacity);
+                if !check_delegated_cell(&delegate_code_hash, i, Source::Output)? {
+                    let capacity = load_cell_capacity(i, Source::Output)?;
+                    out_capacities.push(capacity);
+                }
             },
             Err(SysError::IndexOutOfBound) => break,
             Err(err) => return Err(Error::from(err))
@@ -59,15 +70,19 @@ pub fn main() -> Result<(), Error> {
 
 const ERROR_PUBKEY_BLAKE160_HASH: i32 = -31;
 
+const PUBKEY_HASH_SIZE: usize = 20;
+const CODE_HASH_SIZE: usize = 32;
+
+// wallet args: owner_pubkey_hash(20) | delegate_code_hash(32, optional)
 fn check_owner_mode() -> Result<bool, Error> {
     let script = load_script()?;
     let args: Bytes = script.args().unpack();
     // debug!("script args is {:?}", args);
-    if args.len() != 20 {
+    if args.len() != PUBKEY_HASH_SIZE && args.len() != PUBKEY_HASH_SIZE + CODE_HASH_SIZE {
         return Err(Error::Encoding);
     }
 
-    let error_code = verify_signature(&args.to_vec());
+    let error_code = verify_signature(&args[..PUBKEY_HASH_SIZE].to_vec());
     // debug!("error_code = {}", error_code);
     if error_code == ERROR_PUBKEY_BLAKE160_HASH {
         return Ok(false);
@@ -77,3 +92,20 @@ fn check_owner_mode() -> Result<bool, Error> {
     }
     return Ok(true);
 }
+
+fn get_delegate_code_hash() -> Result<Option<Bytes>, Error> {
+    let args: Bytes = load_script()?.args().unpack();
+    if args.len() == PUBKEY_HASH_SIZE + CODE_HASH_SIZE {
+        return Ok(Some(args.slice(PUBKEY_HASH_SIZE..)));
+    }
+    return Ok(None);
+}
+
+fn check_delegated_cell(delegate_code_hash: &Option<Bytes>, index: usize, source: Source) -> Result<bool, Error> {
+    if let Some(code_hash) = delegate_code_hash {
+        if let Some(type_) = load_cell_type(index, source)? {
+            return Ok(type_.code_hash().raw_data()[..] == code_hash[..]);
+        }
+    }
+    return Ok(false);
+}
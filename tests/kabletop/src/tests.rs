@@ -66,7 +66,16 @@ fn test_success_origin_to_challenge() {
     let (user2_privkey, user2_pkhash) = get_keypair();
 
     // prepare scripts
-    let lock_args_molecule = (500u64, 5u8, 1024u64, blake2b_256([1]), user1_pkhash, get_nfts(5), user2_pkhash, get_nfts(5));
+    let lock_args_molecule = protocol::LockArgs {
+        user_staking_ckb: 500,
+        user_deck_size: 5,
+        begin_blocknumber: 1024,
+        lock_code_hash: blake2b_256([1]),
+        user1_pkhash,
+        user1_nfts: get_nfts(5),
+        user2_pkhash,
+        user2_nfts: get_nfts(5)
+    };
     let lock_args = protocol::lock_args(lock_args_molecule, vec![blake2b_256(luacode)]);
 
     let lock_script = context
@@ -229,7 +238,16 @@ fn test_success_origin_to_settlement() {
 
     // prepare scripts
     let code_hash: [u8; 32] = blake2b_256(ALWAYS_SUCCESS.to_vec());
-    let lock_args_molecule = (500u64, 5u8, 1024u64, code_hash, user1_pkhash, get_nfts(5), user2_pkhash, get_nfts(5));
+    let lock_args_molecule = protocol::LockArgs {
+        user_staking_ckb: 500,
+        user_deck_size: 5,
+        begin_blocknumber: 1024,
+        lock_code_hash: code_hash,
+        user1_pkhash,
+        user1_nfts: get_nfts(5),
+        user2_pkhash,
+        user2_nfts: get_nfts(5)
+    };
     let lock_args = protocol::lock_args(lock_args_molecule, vec![]);
 
     let lock_script = context
@@ -325,7 +343,16 @@ fn test_success_timeout_to_settlement() {
 
     // prepare scripts
     let code_hash: [u8; 32] = blake2b_256(ALWAYS_SUCCESS.to_vec());
-    let lock_args_molecule = (500u64, 5u8, 10000u64, code_hash.clone(), user1_pkhash, get_nfts(5), user2_pkhash, get_nfts(5));
+    let lock_args_molecule = protocol::LockArgs {
+        user_staking_ckb: 500,
+        user_deck_size: 5,
+        begin_blocknumber: 10000,
+        lock_code_hash: code_hash.clone(),
+        user1_pkhash,
+        user1_nfts: get_nfts(5),
+        user2_pkhash,
+        user2_nfts: get_nfts(5)
+    };
     let lock_args = protocol::lock_args(lock_args_molecule, vec![]);
 
     let lock_script = context
@@ -1,11 +1,13 @@
 use super::*;
 use ckb_testtool::{builtin::ALWAYS_SUCCESS, context::Context};
+use ckb_tool::ckb_error::Error;
 use ckb_tool::ckb_types::{
     bytes::Bytes,
-    core::TransactionBuilder,
+    core::{TransactionBuilder, Cycle},
     packed::*,
     prelude::*,
 };
+use error_registry::assert_script_error;
 // use ckb_tool::ckb_error::assert_error_eq;
 // use ckb_tool::ckb_script::ScriptError;
 
@@ -105,3 +107,100 @@ fn test_success() {
         .expect("pass verification");
     println!("consume cycles: {}", cycles);
 }
+
+// transfers nfts between cells of a holder who doesn't own the nft type script
+fn verify_transfer(inputs_data: Vec<Vec<u8>>, outputs_data: Vec<Vec<u8>>) -> Result<Cycle, Error> {
+    // deploy contract
+    let mut context = Context::default();
+    let contract_bin: Bytes = Loader::default().load_binary("nft");
+    let nft_out_point = context.deploy_cell(contract_bin);
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+
+    // prepare cell deps
+    let nft_script_dep = CellDep::new_builder()
+        .out_point(nft_out_point.clone())
+        .build();
+    let always_script_dep = CellDep::new_builder()
+        .out_point(always_success_out_point.clone())
+        .build();
+
+    // prepare scripts, the owner's lock hash shows up in no input
+    let always_script = context
+        .build_script(&always_success_out_point, Default::default())
+        .expect("always script");
+    let nft_script = context
+        .build_script(&nft_out_point, Bytes::from(vec![1u8; 32]))
+        .expect("nft script");
+
+    // prepare cells
+    let inputs = inputs_data
+        .into_iter()
+        .map(|data| {
+            let input_out_point = context.create_cell(
+                CellOutput::new_builder()
+                    .capacity(1000u64.pack())
+                    .lock(always_script.clone())
+                    .type_(Some(nft_script.clone()).pack())
+                    .build(),
+                Bytes::from(data),
+            );
+            CellInput::new_builder()
+                .previous_output(input_out_point)
+                .build()
+        })
+        .collect::<Vec<_>>();
+    let outputs = outputs_data
+        .iter()
+        .map(|_| {
+            CellOutput::new_builder()
+                .capacity(500u64.pack())
+                .lock(always_script.clone())
+                .type_(Some(nft_script.clone()).pack())
+                .build()
+        })
+        .collect::<Vec<_>>();
+    let outputs_data = outputs_data.into_iter().map(Bytes::from).collect::<Vec<_>>();
+
+    // build transaction
+    let tx = TransactionBuilder::default()
+        .inputs(inputs)
+        .outputs(outputs)
+        .outputs_data(outputs_data.pack())
+        .cell_dep(nft_script_dep)
+        .cell_dep(always_script_dep)
+        .build();
+    let tx = context.complete_tx(tx);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_transfer_with_empty_collection() {
+    let nft_data = <[u8; 20]>::from_hex("907e8ee74dc76f8d5b353f41ae96c75fcfe979e5").unwrap().to_vec();
+    let cycles = verify_transfer(vec![nft_data.clone(), vec![]], vec![nft_data, vec![]])
+        .expect("pass test_success_transfer_with_empty_collection");
+    println!("consume cycles: {}", cycles);
+}
+
+// the last nft of a collection is checked just like the others
+#[test]
+fn test_fail_forge_last_nft() {
+    let nft_data: Vec<u8> = [
+        <[u8; 20]>::from_hex("907e8ee74dc76f8d5b353f41ae96c75fcfe979e5").unwrap(),
+        <[u8; 20]>::from_hex("da648442dbb7347e467d1d09da13e5cd3a0ef0e1").unwrap(),
+    ].concat();
+    let forged_data: Vec<u8> = [
+        <[u8; 20]>::from_hex("907e8ee74dc76f8d5b353f41ae96c75fcfe979e5").unwrap(),
+        <[u8; 20]>::from_hex("5ad2c94917e8219b55ccfe910c7e944908ccd4f6").unwrap(),
+    ].concat();
+    let result = verify_transfer(vec![nft_data], vec![forged_data]);
+    assert_script_error!(result, Nft, NFTTransferError);
+}
+
+#[test]
+fn test_fail_transfer_partial_nft() {
+    let nft_data = <[u8; 20]>::from_hex("907e8ee74dc76f8d5b353f41ae96c75fcfe979e5").unwrap().to_vec();
+    let result = verify_transfer(vec![nft_data.clone()], vec![nft_data[..10].to_vec()]);
+    assert_script_error!(result, Nft, NFTDataError);
+}
@@ -30,6 +30,17 @@ pub fn blake160(data: &[u8]) -> [u8; 20] {
 
 #[allow(dead_code)]
 pub fn sign_tx(tx: TransactionView, key: &Privkey) -> TransactionView {
+    sign_tx_with_witness_args(tx, key, WitnessArgs::default())
+}
+
+#[allow(dead_code)]
+pub fn sign_tx_with_witness_args(tx: TransactionView, key: &Privkey, witness: WitnessArgs) -> TransactionView {
+    sign_tx_at_index(tx, key, 0, witness)
+}
+
+// sign the witness of the buyer's first payment input, leaving the other witnesses untouched
+#[allow(dead_code)]
+pub fn sign_tx_at_index(tx: TransactionView, key: &Privkey, index: usize, witness: WitnessArgs) -> TransactionView {
     const SIGNATURE_SIZE: usize = 65;
     let witnesses_len = tx.witnesses().len();
     let tx_hash = tx.hash();
@@ -37,8 +48,7 @@ pub fn sign_tx(tx: TransactionView, key: &Privkey) -> TransactionView {
     let mut blake2b = new_blake2b();
     let mut message = [0u8; 32];
     blake2b.update(&tx_hash.raw_data());
-    // digest the first witness
-    let witness = WitnessArgs::default();
+    // digest the witness at index
     let zero_lock: Bytes = {
         let mut buf = Vec::new();
         buf.resize(SIGNATURE_SIZE, 0);
@@ -55,16 +65,20 @@ pub fn sign_tx(tx: TransactionView, key: &Privkey) -> TransactionView {
     blake2b.finalize(&mut message);
     let message = H256::from(message);
     let sig = key.sign_recoverable(&message).expect("sign");
-    signed_witnesses.push(
-        witness
-            .as_builder()
-            .lock(Some(Bytes::from(sig.serialize())).pack())
-            .build()
-            .as_bytes()
-            .pack(),
-    );
-    for i in 1..witnesses_len {
-        signed_witnesses.push(tx.witnesses().get(i).unwrap());
+    for i in 0..witnesses_len.max(index + 1) {
+        if i == index {
+            signed_witnesses.push(
+                witness
+                    .clone()
+                    .as_builder()
+                    .lock(Some(Bytes::from(sig.serialize())).pack())
+                    .build()
+                    .as_bytes()
+                    .pack(),
+            );
+        } else {
+            signed_witnesses.push(tx.witnesses().get(i).unwrap_or_default());
+        }
     }
     tx.as_advanced_builder()
         .set_witnesses(signed_witnesses)
@@ -1,25 +1,44 @@
 use super::{
-    helper::{sign_tx, blake160, MAX_CYCLES},
+    helper::{sign_tx, sign_tx_with_witness_args, sign_tx_at_index, blake160, MAX_CYCLES},
     *,
 };
 use ckb_system_scripts::BUNDLED_CELL;
+use error_registry::assert_script_error;
+use codec::{
+    config::{self, NftConfig, PityRule, SaleWindow, EXTENSION_SUDT_PRICE, EXTENSION_PITY_RULE, EXTENSION_LIMITED_SALE, EXTENSION_SALE_WINDOW,
+        EXTENSION_PURCHASE_LIMIT, EXTENSION_WHITELIST, EXTENSION_REVENUE_SPLIT, EXTENSION_OPERATOR, REVENUE_SHARE_TOTAL},
+    payment::*,
+    sale::{SaleArgs, encode_sale_stock},
+    sealed_pack::SealedPack,
+    collection::encode_nft_collection
+};
+use std::convert::TryInto;
 use ckb_testtool::{
     builtin::ALWAYS_SUCCESS,
     context::Context
 };
 use ckb_tool::{
-    ckb_crypto::secp::Generator,
+    ckb_crypto::secp::{Generator, Privkey},
+    ckb_error::Error,
     ckb_hash::blake2b_256,
     ckb_types::{
         bytes::Bytes,
-        core::{TransactionBuilder, TransactionView, HeaderBuilder},
-        packed::{CellDep, CellOutput, CellInput, Byte32, Script},
+        core::{TransactionBuilder, TransactionView, HeaderBuilder, HeaderView, Cycle},
+        packed::{CellDep, CellOutput, CellInput, Byte32, Script, WitnessArgs},
         prelude::*,
     },
 };
 
 type ParamType = (Bytes, u64, Bytes, Option<Bytes>);
 
+const CKB_SHANNONS: u64 = 100_000_000;
+const REFUND_DELAY_BLOCKS: u64 = 100_000;
+const SINCE_RELATIVE_BLOCK_NUMBER_FLAG: u64 = 0x8000_0000_0000_0000;
+const PURCHASE_BLOCK: u64 = 1000;
+const REVEAL_DELAY: u64 = 10;
+const LOTTERY_SIZE: usize = 8;
+const HASH_TYPE_DATA: u8 = 0;
+const HASH_TYPE_TYPE: u8 = 1;
 const TRANSACTIONS_ROOT_SOURCE: u8 = 100;
 const UNCLES_HASH_SOURCE: u8 = 200;
 
@@ -55,10 +74,8 @@ fn build_partial_tx(
         .out_point(always_success_out_point.clone())
         .build();
 
-    // deploy lottery header
-    let header = HeaderBuilder::default()
-        .transactions_root(Byte32::new(blake2b_256(TRANSACTIONS_ROOT_SOURCE.to_le_bytes())))
-        .build();
+    // deploy purchase header
+    let header = build_purchase_header();
     context.insert_header(header.clone());
 
     // prepare input cells
@@ -150,71 +167,237 @@ fn build_partial_tx(
         .build()
 }
 
-fn build_nft_config(price: u64, count: u8, config: Vec<([u8; 20], u8)>) -> Bytes {
-    let mut data = vec![];
-    data.append(&mut price.to_le_bytes().to_vec());
-    data.append(&mut count.to_le_bytes().to_vec());
-    for &(nft, rate) in config.iter() {
-        data.append(&mut nft.to_vec());
-        data.append(&mut rate.to_le_bytes().to_vec());
+fn build_purchase_header() -> HeaderView {
+    HeaderBuilder::default()
+        .number(PURCHASE_BLOCK.pack())
+        .transactions_root(Byte32::new(blake2b_256(TRANSACTIONS_ROOT_SOURCE.to_le_bytes())))
+        .build()
+}
+
+fn build_reveal_header(number: u64) -> HeaderView {
+    HeaderBuilder::default()
+        .number(number.pack())
+        .uncles_hash(Byte32::new(blake2b_256(UNCLES_HASH_SOURCE.to_le_bytes())))
+        .build()
+}
+
+fn append_reveal_header(context: &mut Context, tx: TransactionView, number: u64) -> (TransactionView, HeaderView) {
+    let header = build_reveal_header(number);
+    context.insert_header(header.clone());
+    let tx = tx
+        .as_advanced_builder()
+        .header_dep(header.hash())
+        .build();
+    (tx, header)
+}
+
+fn build_lottery_seed(reveal_header: &HeaderView, payment_script_hash: &Byte32) -> [u8; 32] {
+    let mut seed = vec![];
+    seed.append(&mut build_purchase_header().hash().raw_data().to_vec());
+    seed.append(&mut reveal_header.hash().raw_data().to_vec());
+    seed.append(&mut payment_script_hash.raw_data().to_vec());
+    blake2b_256(seed)
+}
+
+// keypair whose pubkey hash serves as ownerlock args of a composer or payment type args of a buyer
+struct Wallet {
+    args: Bytes,
+    privkey: Privkey
+}
+
+impl Wallet {
+    fn random() -> Self {
+        let keypair = Generator::random_keypair();
+        let compressed_pubkey = keypair.1.serialize();
+        let args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+        Wallet { args, privkey: keypair.0 }
+    }
+}
+
+// revealed nfts are locked by the buyer and typed by always-success script with the ownerlock hash as args
+fn append_nft_output(context: &mut Context, tx: TransactionView, owner: &Wallet, ownerlock_hash: Bytes, nft_collection: Bytes) -> TransactionView {
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&always_success_out_point, owner.args.clone())
+        .expect("build nft lock_script");
+    let type_script = context
+        .build_script(&always_success_out_point, ownerlock_hash)
+        .expect("build nft type_script");
+    tx.as_advanced_builder()
+        .output(build_output(&100, lock_script, Some(type_script)))
+        .output_data(nft_collection.pack())
+        .build()
+}
+
+// every signer signs the witness at the index of their first payment input
+fn sign_and_verify(context: &mut Context, tx: TransactionView, signers: Vec<(&Wallet, usize)>) -> Result<Cycle, Error> {
+    let mut tx = context.complete_tx(tx);
+    for (wallet, index) in signers {
+        tx = sign_tx_at_index(tx, &wallet.privkey, index, WitnessArgs::default());
     }
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+fn build_nft_config(series_id: u32, price: u64, count: u8, reveal_delay: u64, config: Vec<([u8; 20], u32)>) -> Bytes {
+    // revealed nfts are typed by always-success script in tests
+    let nft_code_hash = CellOutput::calc_data_hash(&ALWAYS_SUCCESS).raw_data();
+    build_nft_config_with_type(series_id, price, count, reveal_delay, &nft_code_hash, HASH_TYPE_DATA, config)
+}
+
+fn build_nft_config_with_type(
+    series_id: u32, price: u64, count: u8, reveal_delay: u64, nft_code_hash: &[u8], nft_hash_type: u8, config: Vec<([u8; 20], u32)>
+) -> Bytes {
+    let config = NftConfig {
+        series_id,
+        ckb_price_perpack: price,
+        nft_count_perpack: count,
+        reveal_delay,
+        nft_code_hash: nft_code_hash.try_into().unwrap(),
+        nft_hash_type,
+        nfts: config,
+        ..Default::default()
+    };
+    Bytes::from(config.encode())
+}
+
+// payment data snapshots the draw rules of the config, not its whole data
+fn build_config_hash(nft_data: &Bytes) -> [u8; 32] {
+    let config = NftConfig::decode(nft_data).expect("decode nft config");
+    blake2b_256(config.encode_draw_rules())
+}
+
+fn append_nft_config_extension(nft_data: Bytes, extension_type: u8, extension_data: Vec<u8>) -> Bytes {
+    let mut data = nft_data.to_vec();
+    config::append_extension(&mut data, extension_type, &extension_data);
     Bytes::from(data)
 }
 
-fn build_nft_collection(config: Vec<([u8; 20], u8)>, count: usize) -> Bytes {
-    let header = HeaderBuilder::default()
-        .transactions_root(Byte32::new(blake2b_256(TRANSACTIONS_ROOT_SOURCE.to_le_bytes())))
-        // .uncles_hash(Byte32::new(blake2b_256(UNCLES_HASH_SOURCE.to_le_bytes())))
-        .build();
+fn build_counter_payment_data(count: u32) -> Bytes {
+    let payment = PaymentData { version: PAYMENT_DATA_VERSION_COUNTER, count, ..Default::default() };
+    Bytes::from(payment.encode())
+}
+
+fn build_payment_data(count: u32, series_id: u32) -> Bytes {
+    let payment = PaymentData { version: PAYMENT_DATA_VERSION_SERIES, count, series_id, ..Default::default() };
+    Bytes::from(payment.encode())
+}
+
+fn build_refundable_payment_data(count: u32, series_id: u32, paid: u64) -> Bytes {
+    let payment = PaymentData { version: PAYMENT_DATA_VERSION_REFUNDABLE, count, series_id, paid, ..Default::default() };
+    Bytes::from(payment.encode())
+}
+
+fn build_pity_payment_data(count: u32, series_id: u32, paid: u64, packs_since_hit: u32) -> Bytes {
+    let payment = PaymentData {
+        version: PAYMENT_DATA_VERSION_PITY, count, series_id, paid, packs_since_hit, ..Default::default()
+    };
+    Bytes::from(payment.encode())
+}
+
+fn build_lifetime_payment_data(count: u32, series_id: u32, paid: u64, packs_since_hit: u32, lifetime_count: u32) -> Bytes {
+    let payment = PaymentData {
+        version: PAYMENT_DATA_VERSION_LIFETIME, count, series_id, paid, packs_since_hit, lifetime_count, ..Default::default()
+    };
+    Bytes::from(payment.encode())
+}
+
+fn build_snapshot_payment_data(count: u32, paid: u64, lifetime_count: u32, config_hash: [u8; 32]) -> Bytes {
+    let payment = PaymentData {
+        version: PAYMENT_DATA_VERSION_SNAPSHOT, count, paid, lifetime_count, config_hash, ..Default::default()
+    };
+    Bytes::from(payment.encode())
+}
+
+fn build_partial_payment_data(count: u32, draw_offset: u32, anchor_block: u64, config_hash: [u8; 32]) -> Bytes {
+    let payment = PaymentData {
+        version: PAYMENT_DATA_VERSION_PARTIAL, count, lifetime_count: 4, config_hash, draw_offset, anchor_block, ..Default::default()
+    };
+    Bytes::from(payment.encode())
+}
+
+// packs bought on top of unrevealed ones are drawn from the block of the first purchase, which
+// only payment data able to record it can keep
+fn build_anchored_payment_data(count: u32, paid: u64, lifetime_count: u32, anchor_block: u64) -> Bytes {
+    let payment = PaymentData {
+        version: PAYMENT_DATA_VERSION_PARTIAL, count, paid, lifetime_count, config_hash: build_config_hash(&starter_series()),
+        anchor_block, ..Default::default()
+    };
+    Bytes::from(payment.encode())
+}
+
+// packs capped per buyer are only sold into the payment cell the composer registered at its creation
+fn build_registered_payment_data(count: u32, paid: u64, lifetime_count: u32, registered: bool) -> Bytes {
+    let payment = PaymentData {
+        version: PAYMENT_DATA_VERSION_REGISTERED, count, paid, lifetime_count, config_hash: build_config_hash(&starter_series()),
+        registered, ..Default::default()
+    };
+    Bytes::from(payment.encode())
+}
+
+// sealed packs of the starter series are minted under its draw rules
+fn build_sealed_pack_data(series_id: u32, count: u32, anchor_block: u64, pack_id: [u8; 32]) -> Bytes {
+    build_sealed_pack_data_with_rules(series_id, count, anchor_block, pack_id, build_config_hash(&starter_series()))
+}
+
+fn build_sealed_pack_data_with_rules(series_id: u32, count: u32, anchor_block: u64, pack_id: [u8; 32], draw_hash: [u8; 32]) -> Bytes {
+    let sealed_pack = SealedPack { series_id, count, anchor_block, pack_id, draw_hash };
+    Bytes::from(sealed_pack.encode())
+}
 
-    let mut lotteries = header.hash().raw_data().to_vec();
+fn build_nft_collection(config: Vec<([u8; 20], u32)>, count: usize, seed: [u8; 32]) -> Bytes {
+    let mut lotteries = seed.to_vec();
     println!("lotteries = {:?}", lotteries);
 
-    let mut collection: Vec<u8> = vec![];
+    let total_weight = config.iter().map(|&(_, weight)| weight as u64).sum::<u64>();
+    let mut collection: Vec<[u8; 20]> = vec![];
     for i in 0..count {
-        if i >= lotteries.len() {
+        while (i + 1) * LOTTERY_SIZE > lotteries.len() {
             let next_hash = blake2b_256(lotteries.clone());
             lotteries.append(&mut next_hash.to_vec());
             println!("next lotteries = {:?}", lotteries);
         }
-        let lottery = lotteries[i];
-        let mut expect_nft: Option<[u8; 20]> = None;
-        for &(nft, rate) in config.iter() {
-            if lottery < rate {
-                expect_nft = Some(nft);
+        let lottery_bytes: [u8; LOTTERY_SIZE] = lotteries[i * LOTTERY_SIZE..(i + 1) * LOTTERY_SIZE].try_into().unwrap();
+        let lottery = u64::from_le_bytes(lottery_bytes) % total_weight;
+        let mut weight_sum = 0u64;
+        for &(nft, weight) in config.iter() {
+            weight_sum += weight as u64;
+            if lottery < weight_sum {
+                collection.push(nft);
                 break;
             }
         }
-        if let Some(nft) = expect_nft {
-            collection.append(&mut nft.to_vec());
-        } else {
-            let &(nft, _) = config.iter().last().unwrap();
-            collection.append(&mut nft.to_vec());
-        }
     }
 
-    Bytes::from(collection)
+    Bytes::from(encode_nft_collection(&collection))
 }
 
-fn right_nfts() -> Vec<([u8; 20], u8)> {
+fn right_nfts() -> Vec<([u8; 20], u32)> {
     vec![
         (blake160(&[1u8]), 56),
-        (blake160(&[2u8]), 86),
-        (blake160(&[3u8]), 101),
-        (blake160(&[4u8]), 134),
-        (blake160(&[5u8]), 180),
-        (blake160(&[6u8]), 255),
+        (blake160(&[2u8]), 30),
+        (blake160(&[3u8]), 15),
+        (blake160(&[4u8]), 33),
+        (blake160(&[5u8]), 46),
+        (blake160(&[6u8]), 75),
+    ]
+}
+
+fn wrong_nfts() -> Vec<([u8; 20], u32)> {
+    vec![
+        (blake160(&[2u8]) /*1*/, 56),
+        (blake160(&[3u8]) /*2*/, 30),
+        (blake160(&[4u8]) /*3*/, 15),
+        (blake160(&[5u8]) /*4*/, 33),
+        (blake160(&[6u8]) /*5*/, 46),
+        (blake160(&[1u8]) /*6*/, 75),
     ]
 }
 
-fn wrong_nfts() -> Vec<([u8; 20], u8)> {
+fn legendary_nfts() -> Vec<([u8; 20], u32)> {
     vec![
-        (blake160(&[1u8]), 86 /*56*/),
-        (blake160(&[2u8]), 56 /*86*/),
-        (blake160(&[3u8]), 101),
-        (blake160(&[4u8]), 134),
-        (blake160(&[5u8]), 180),
-        (blake160(&[6u8]), 255),
+        (blake160(&[7u8]), 1),     // legendary, 1 in 5000
+        (blake160(&[8u8]), 999),
+        (blake160(&[9u8]), 4000),
     ]
 }
 
@@ -229,7 +412,7 @@ fn test_success_compose_nft() {
     let privkey = keypair.0;
 
     // prepare composer nft config
-    let nft_data = build_nft_config(100, 5, right_nfts());
+    let nft_data = build_nft_config(0, 100, 5, REVEAL_DELAY, right_nfts());
 
     // build partial tx
     let tx = build_partial_tx(
@@ -261,8 +444,8 @@ fn test_success_update_composed_nft() {
     let privkey = keypair.0;
 
     // prepare composer nft config
-    let nft_data_old = build_nft_config(100, 5, right_nfts());
-    let nft_data_new = build_nft_config(150, 5, right_nfts());
+    let nft_data_old = build_nft_config(0, 100, 5, REVEAL_DELAY, right_nfts());
+    let nft_data_new = build_nft_config(0, 150, 5, REVEAL_DELAY, right_nfts());
 
     // build partial tx
     let tx = build_partial_tx(
@@ -283,8 +466,7 @@ fn test_success_update_composed_nft() {
     println!("consume cycles: {}", cycles);
 }
 
-#[test]
-fn test_success_create_wallet() {
+fn verify_wallet_creation(new_data: Bytes) -> Result<Cycle, Error> {
     let mut context = Context::default();
 
     // create composer keypair
@@ -302,7 +484,7 @@ fn test_success_create_wallet() {
     let tx = build_partial_tx(
         &mut context,
         vec![(Bytes::new(), 1000, user_args.clone(), None)],
-        vec![(Bytes::from(vec![0]), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(new_data, 1000, composer_args.clone(), Some(user_args.clone()))],
         vec![]
     );
 
@@ -311,12 +493,57 @@ fn test_success_create_wallet() {
     let tx = sign_tx(tx, &user_privkey);
 
     // run
-    let cycles = context
-        .verify_tx(&tx, MAX_CYCLES)
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_create_wallet() {
+    let cycles = verify_wallet_creation(Bytes::from(vec![0]))
         .expect("pass test_success_create_wallet");
     println!("consume cycles: {}", cycles);
 }
 
+#[test]
+fn test_fail_create_wallet_with_prefilled_paid() {
+    let result = verify_wallet_creation(build_refundable_payment_data(0, 0, 300));
+    assert_script_error!(result, Payment, InvalidWalletCreationFormat);
+}
+
+// a buyer can't register their own payment cell, or the purchase limit could be reset by creating another one
+#[test]
+fn test_fail_create_wallet_registered_by_buyer() {
+    let registered = PaymentData { version: PAYMENT_DATA_VERSION_REGISTERED, registered: true, ..Default::default() };
+    let result = verify_wallet_creation(Bytes::from(registered.encode()));
+    assert_script_error!(result, Payment, InvalidWalletCreationFormat);
+}
+
+#[test]
+fn test_success_create_wallet_registered_by_composer() {
+    let mut context = Context::default();
+    let composer = Wallet::random();
+    let buyer = Wallet::random();
+
+    // build partial tx which the composer funds from their own ownerlock
+    let registered = PaymentData { version: PAYMENT_DATA_VERSION_REGISTERED, registered: true, ..Default::default() };
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(Bytes::new(), 1000, composer.args.clone(), None)],
+        vec![(Bytes::from(registered.encode()), 1000, composer.args.clone(), Some(buyer.args.clone()))],
+        vec![]
+    );
+
+    // run
+    let cycles = sign_and_verify(&mut context, tx, vec![(&composer, 0)])
+        .expect("pass test_success_create_wallet_registered_by_composer");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_create_wallet_with_prefilled_config_hash() {
+    let result = verify_wallet_creation(build_snapshot_payment_data(0, 0, 0, build_config_hash(&starter_series())));
+    assert_script_error!(result, Payment, InvalidWalletCreationFormat);
+}
+
 #[test]
 fn test_success_purchase_nft_package() {
     let mut context = Context::default();
@@ -333,7 +560,7 @@ fn test_success_purchase_nft_package() {
     let user_privkey = keypair_user.0;
 
     // prepare composer nft config
-    let nft_data = build_nft_config(100, 5, right_nfts());
+    let nft_data = build_nft_config(0, 100, 5, REVEAL_DELAY, right_nfts());
 
     // build partial tx
     let tx = build_partial_tx(
@@ -354,8 +581,7 @@ fn test_success_purchase_nft_package() {
     println!("consume cycles: {}", cycles);
 }
 
-#[test]
-fn test_success_transfer_from_wallet() {
+fn verify_composer_withdrawal(old_data: Bytes, old_capacity: u64, new_data: Bytes, new_capacity: u64) -> Result<Cycle, Error> {
     let mut context = Context::default();
 
     // create composer keypair
@@ -372,8 +598,8 @@ fn test_success_transfer_from_wallet() {
     // build partial tx
     let tx = build_partial_tx(
         &mut context,
-        vec![(Bytes::from(vec![0]), 500, composer_args.clone(), Some(user_args.clone()))],
-        vec![(Bytes::from(vec![0]), 100, composer_args.clone(), Some(user_args.clone()))],
+        vec![(old_data, old_capacity, composer_args.clone(), Some(user_args.clone()))],
+        vec![(new_data, new_capacity, composer_args.clone(), Some(user_args.clone()))],
         vec![]
     );
 
@@ -382,12 +608,78 @@ fn test_success_transfer_from_wallet() {
     let tx = sign_tx(tx, &composer_privkey);
 
     // run
-    let cycles = context
-        .verify_tx(&tx, MAX_CYCLES)
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_transfer_from_wallet() {
+    let cycles = verify_composer_withdrawal(Bytes::from(vec![0]), 500, Bytes::from(vec![0]), 100)
         .expect("pass test_success_transfer_from_wallet");
     println!("consume cycles: {}", cycles);
 }
 
+// occupied capacity of the payment cell is counted in real shannons, so these use whole ckb
+#[test]
+fn test_success_transfer_from_wallet_above_refundable_paid() {
+    let old_data = build_anchored_payment_data(3, 300 * CKB_SHANNONS, 3, 0);
+    let new_data = build_anchored_payment_data(3, 300 * CKB_SHANNONS, 3, PURCHASE_BLOCK);
+    let cycles = verify_composer_withdrawal(old_data, 1000 * CKB_SHANNONS, new_data, 500 * CKB_SHANNONS)
+        .expect("pass test_success_transfer_from_wallet_above_refundable_paid");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_transfer_refundable_paid_from_wallet() {
+    let old_data = build_anchored_payment_data(3, 300 * CKB_SHANNONS, 3, 0);
+    let new_data = build_anchored_payment_data(3, 300 * CKB_SHANNONS, 3, PURCHASE_BLOCK);
+    let result = verify_composer_withdrawal(old_data, 1000 * CKB_SHANNONS, new_data, 400 * CKB_SHANNONS);
+    assert_script_error!(result, Payment, InvalidTransferFormat);
+}
+
+// moving the payment cell would otherwise move the purchase block that unrevealed packs are drawn from
+#[test]
+fn test_fail_transfer_from_wallet_without_recording_purchase_block() {
+    let old_data = build_anchored_payment_data(3, 0, 3, 0);
+    let result = verify_composer_withdrawal(old_data.clone(), 500, old_data, 100);
+    assert_script_error!(result, Payment, InvalidTransferFormat);
+}
+
+#[test]
+fn test_fail_transfer_from_wallet_holding_unrecordable_packs() {
+    let old_data = build_payment_data(3, 0);
+    let result = verify_composer_withdrawal(old_data.clone(), 500, old_data, 100);
+    assert_script_error!(result, Payment, InvalidTransferFormat);
+}
+
+// the attacker withdraws from their own wallet to pass as a composer, while draining the buyer's
+// payment cell kept in the wallet of another composer in the same script group
+#[test]
+fn test_fail_transfer_from_foreign_wallet_in_same_group() {
+    let mut context = Context::default();
+    let composer = Wallet::random();
+    let attacker = Wallet::random();
+    let user = Wallet::random();
+
+    // build partial tx
+    let payment_data = build_payment_data(3, 0);
+    let tx = build_partial_tx(
+        &mut context,
+        vec![
+            (payment_data.clone(), 500, attacker.args.clone(), Some(user.args.clone())),
+            (payment_data.clone(), 1300, composer.args.clone(), Some(user.args.clone()))
+        ],
+        vec![
+            (payment_data.clone(), 400, attacker.args.clone(), Some(user.args.clone())),
+            (payment_data, 100, composer.args.clone(), Some(user.args.clone()))
+        ],
+        vec![]
+    );
+
+    // run
+    let result = sign_and_verify(&mut context, tx, vec![(&attacker, 0)]);
+    assert_script_error!(result, Payment, InvalidGiftOperation);
+}
+
 #[test]
 fn test_success_reveal_nft_package() {
     let mut context = Context::default();
@@ -403,9 +695,8 @@ fn test_success_reveal_nft_package() {
     let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
     let user_privkey = keypair_user.0;
 
-    // prepare composer nft config and collection
-    let nft_data = build_nft_config(100, 5, right_nfts());
-    let nft_collection = build_nft_collection(right_nfts(), 4);
+    // prepare composer nft config
+    let nft_data = build_nft_config(0, 100, 5, REVEAL_DELAY, right_nfts());
 
     // build partial tx
     let tx = build_partial_tx(
@@ -415,6 +706,12 @@ fn test_success_reveal_nft_package() {
         vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
     );
 
+    // pin reveal header REVEAL_DELAY blocks after the purchase and draw the expected collection
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let payment_script_hash = tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+    let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+    let nft_collection = build_nft_collection(right_nfts(), 4, seed);
+
     // append nft contract output
     let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
     let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
@@ -440,3 +737,2264 @@ fn test_success_reveal_nft_package() {
         .expect("pass test_success_reveal_nft_package");
     println!("consume cycles: {}", cycles);
 }
+
+#[test]
+fn test_success_purchase_nft_package_incrementally() {
+    // buy 1, then 2, then 3 packages on top of the unrevealed ones, which keep the first purchase block
+    let purchases = vec![
+        (build_anchored_payment_data(0, 0, 0, 0), 1000u64, build_anchored_payment_data(1, 100, 1, 0), 1100u64),
+        (build_anchored_payment_data(1, 100, 1, 0), 1100, build_anchored_payment_data(3, 300, 3, PURCHASE_BLOCK), 1300),
+        (build_anchored_payment_data(3, 300, 3, PURCHASE_BLOCK), 1300, build_anchored_payment_data(6, 600, 6, PURCHASE_BLOCK), 1600)
+    ];
+    for (old_data, old_capacity, new_data, new_capacity) in purchases {
+        let cycles = verify_payment_data_update(old_data, old_capacity, new_data, new_capacity)
+            .expect("pass test_success_purchase_nft_package_incrementally");
+        println!("consume cycles: {}", cycles);
+    }
+}
+
+#[test]
+fn test_fail_purchase_nft_package_incrementally_underpaid() {
+    // 3 more packages paid as 2
+    let result = verify_payment_data_update(
+        build_anchored_payment_data(3, 300, 3, PURCHASE_BLOCK), 1300,
+        build_anchored_payment_data(6, 500, 6, PURCHASE_BLOCK), 1500
+    );
+    assert_script_error!(result, Payment, InsufficientCapacity);
+}
+
+// buying more packs must not move the purchase block, or the buyer could re-roll the unrevealed ones
+#[test]
+fn test_fail_purchase_nft_package_incrementally_without_recording_purchase_block() {
+    let result = verify_payment_data_update(
+        build_anchored_payment_data(1, 100, 1, 0), 1100,
+        build_anchored_payment_data(3, 300, 3, 0), 1300
+    );
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_on_top_of_unrecordable_packs() {
+    let result = verify_payment_data_update(build_payment_data(1, 0), 1100, build_payment_data(3, 0), 1300);
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+#[test]
+fn test_success_reveal_accumulated_nft_packages() {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // prepare composer nft config
+    let nft_data = build_nft_config(0, 100, 5, REVEAL_DELAY, right_nfts());
+
+    // build partial tx
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(Bytes::from(vec![6]), 1600, composer_args.clone(), Some(user_args.clone()))],
+        vec![(Bytes::from(vec![0]), 1600, composer_args.clone(), Some(user_args.clone()))],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // pin reveal header REVEAL_DELAY blocks after the purchase and draw the expected collection
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let payment_script_hash = tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+    let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+    let nft_collection = build_nft_collection(right_nfts(), 6 * 5, seed);
+
+    // append nft contract output
+    let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&always_success_out_point, user_args)
+        .expect("build nft lock_script");
+    let type_script = context
+        .build_script(&always_success_out_point, lock_hash.raw_data())
+        .expect("build nft type_script");
+    let tx = tx
+        .as_advanced_builder()
+        .output(build_output(&100, lock_script, Some(type_script)))
+        .output_data(nft_collection.pack())
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    let cycles = context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass test_success_reveal_accumulated_nft_packages");
+    println!("consume cycles: {}", cycles);
+}
+
+fn verify_payment_data_update(old_data: Bytes, old_capacity: u64, new_data: Bytes, new_capacity: u64) -> Result<Cycle, Error> {
+    let nft_data = build_nft_config(0, 100, 5, REVEAL_DELAY, right_nfts());
+    verify_payment_data_update_with_config(nft_data, old_data, old_capacity, new_data, new_capacity)
+}
+
+fn verify_payment_data_update_with_config(
+    nft_data: Bytes, old_data: Bytes, old_capacity: u64, new_data: Bytes, new_capacity: u64
+) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // build partial tx
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(old_data, old_capacity, composer_args.clone(), Some(user_args.clone()))],
+        vec![(new_data, new_capacity, composer_args.clone(), Some(user_args.clone()))],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_upgrade_legacy_payment_data() {
+    let cycles = verify_payment_data_update(Bytes::from(vec![0]), 1000, build_payment_data(3, 0), 1300)
+        .expect("pass test_success_upgrade_legacy_payment_data");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_success_purchase_beyond_legacy_limit() {
+    let cycles = verify_payment_data_update(build_counter_payment_data(0), 1000, build_counter_payment_data(300), 31000)
+        .expect("pass test_success_purchase_beyond_legacy_limit");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_success_upgrade_counter_payment_data() {
+    let cycles = verify_payment_data_update(build_counter_payment_data(0), 1200, build_payment_data(3, 0), 1500)
+        .expect("pass test_success_upgrade_counter_payment_data");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_decrease_pack_counter_without_reveal() {
+    let result = verify_payment_data_update(build_payment_data(10, 0), 1000, build_payment_data(5, 0), 1000);
+    assert_script_error!(result, Payment, PackCounterUnderflow);
+}
+
+#[test]
+fn test_fail_downgrade_payment_data() {
+    let result = verify_payment_data_update(build_payment_data(2, 0), 1000, Bytes::from(vec![3]), 1100);
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+#[test]
+fn test_fail_reveal_nft_package_before_delay() {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // prepare composer nft config
+    let nft_data = build_nft_config(0, 100, 5, REVEAL_DELAY, right_nfts());
+
+    // build partial tx
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(Bytes::from(vec![1]), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(Bytes::from(vec![0]), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // reveal with a header that is one block earlier than the composer requires
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY - 1);
+    let payment_script_hash = tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+    let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+    let nft_collection = build_nft_collection(right_nfts(), 4, seed);
+
+    // append nft contract output
+    let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&always_success_out_point, user_args)
+        .expect("build nft lock_script");
+    let type_script = context
+        .build_script(&always_success_out_point, lock_hash.raw_data())
+        .expect("build nft type_script");
+    let tx = tx
+        .as_advanced_builder()
+        .output(build_output(&100, lock_script, Some(type_script)))
+        .output_data(nft_collection.pack())
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    let result = context.verify_tx(&tx, MAX_CYCLES);
+    assert_script_error!(result, Payment, MissingRevealHeader);
+}
+
+fn verify_reveal_with_nfts(config_nfts: Vec<([u8; 20], u32)>, revealed_nfts: Vec<([u8; 20], u32)>, buy_count: u8) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // prepare composer nft config
+    let nft_data = build_nft_config(0, 100, 5, REVEAL_DELAY, config_nfts);
+
+    // build partial tx
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(Bytes::from(vec![buy_count]), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(Bytes::from(vec![0]), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // pin reveal header REVEAL_DELAY blocks after the purchase and draw the expected collection
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let payment_script_hash = tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+    let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+    let nft_collection = build_nft_collection(revealed_nfts, buy_count as usize * 5, seed);
+
+    // append nft contract output
+    let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&always_success_out_point, user_args)
+        .expect("build nft lock_script");
+    let type_script = context
+        .build_script(&always_success_out_point, lock_hash.raw_data())
+        .expect("build nft type_script");
+    let tx = tx
+        .as_advanced_builder()
+        .output(build_output(&100, lock_script, Some(type_script)))
+        .output_data(nft_collection.pack())
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_reveal_nft_package_with_legendary_weights() {
+    let cycles = verify_reveal_with_nfts(legendary_nfts(), legendary_nfts(), 4)
+        .expect("pass test_success_reveal_nft_package_with_legendary_weights");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_reveal_nft_package_with_wrong_nfts() {
+    let result = verify_reveal_with_nfts(right_nfts(), wrong_nfts(), 1);
+    assert_script_error!(result, Payment, InvalidRevealNFTData);
+}
+
+fn starter_series() -> Bytes {
+    build_nft_config(0, 100, 5, REVEAL_DELAY, right_nfts())
+}
+
+fn expansion_series() -> Bytes {
+    build_nft_config(1, 300, 5, REVEAL_DELAY, legendary_nfts())
+}
+
+fn verify_series_operation(
+    old_data: Bytes,
+    old_capacity: u64,
+    new_data: Bytes,
+    new_capacity: u64,
+    nft_configs: Vec<Bytes>,
+    revealed_opt: Option<(Vec<([u8; 20], u32)>, usize)>
+) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // build partial tx with every composer nft series config
+    let deps = nft_configs
+        .into_iter()
+        .map(|nft_data| (nft_data, 0, composer_args.clone(), Some(composer_args.clone())))
+        .collect::<Vec<ParamType>>();
+    let mut tx = build_partial_tx(
+        &mut context,
+        vec![(old_data, old_capacity, composer_args.clone(), Some(user_args.clone()))],
+        vec![(new_data, new_capacity, composer_args.clone(), Some(user_args.clone()))],
+        deps
+    );
+
+    // append nft contract output drawn from the revealed series
+    if let Some((revealed_nfts, count)) = revealed_opt {
+        let (reveal_tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+        let payment_script_hash = reveal_tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+        let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+        let nft_collection = build_nft_collection(revealed_nfts, count, seed);
+        let lock_hash = reveal_tx.output(0).unwrap().lock().calc_script_hash();
+        let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+        let lock_script = context
+            .build_script(&always_success_out_point, user_args)
+            .expect("build nft lock_script");
+        let type_script = context
+            .build_script(&always_success_out_point, lock_hash.raw_data())
+            .expect("build nft type_script");
+        tx = reveal_tx
+            .as_advanced_builder()
+            .output(build_output(&100, lock_script, Some(type_script)))
+            .output_data(nft_collection.pack())
+            .build();
+    }
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_purchase_expansion_series() {
+    let cycles = verify_series_operation(
+        build_payment_data(0, 0), 1000, build_payment_data(2, 1), 1600,
+        vec![starter_series(), expansion_series()], None
+    ).expect("pass test_success_purchase_expansion_series");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_expansion_series_at_starter_price() {
+    let result = verify_series_operation(
+        build_payment_data(0, 0), 1000, build_payment_data(2, 1), 1200,
+        vec![starter_series(), expansion_series()], None
+    );
+    assert_script_error!(result, Payment, InsufficientCapacity);
+}
+
+#[test]
+fn test_fail_mix_series_in_unrevealed_packages() {
+    let result = verify_series_operation(
+        build_payment_data(2, 0), 1000, build_payment_data(3, 1), 1300,
+        vec![starter_series(), expansion_series()], None
+    );
+    assert_script_error!(result, Payment, SeriesMismatch);
+}
+
+#[test]
+fn test_fail_duplicate_series_config() {
+    let result = verify_series_operation(
+        build_payment_data(0, 0), 1000, build_payment_data(1, 0), 1100,
+        vec![starter_series(), build_nft_config(0, 50, 5, REVEAL_DELAY, right_nfts())], None
+    );
+    assert_script_error!(result, Payment, DumplicateDepCell);
+}
+
+#[test]
+fn test_success_reveal_expansion_series() {
+    let cycles = verify_series_operation(
+        build_payment_data(2, 1), 1600, build_payment_data(0, 1), 1600,
+        vec![starter_series(), expansion_series()], Some((legendary_nfts(), 2 * 5))
+    ).expect("pass test_success_reveal_expansion_series");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_reveal_expansion_series_with_starter_table() {
+    let result = verify_series_operation(
+        build_payment_data(2, 1), 1600, build_payment_data(0, 1), 1600,
+        vec![starter_series(), expansion_series()], Some((right_nfts(), 2 * 5))
+    );
+    assert_script_error!(result, Payment, InvalidRevealNFTData);
+}
+
+fn verify_sudt_purchase(
+    new_data: Bytes, sudt_price: u128, old_amount: u128, new_amount: u128, friend_data_opt: Option<Bytes>
+) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // prepare a mocked sUDT type script and composer's wallet lock
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let sudt_script = context
+        .build_script(&always_success_out_point, Bytes::from(b"sudt".to_vec()))
+        .expect("build sudt type_script");
+    let wallet_script = context
+        .build_script(&always_success_out_point, composer_args.clone())
+        .expect("build wallet lock_script");
+
+    // prepare composer nft config priced in sUDT
+    let sudt_type_hash = sudt_script.calc_script_hash().unpack();
+    let sudt_extension = config::encode_sudt_price(&sudt_type_hash, sudt_price);
+    let nft_data = append_nft_config_extension(starter_series(), EXTENSION_SUDT_PRICE, sudt_extension);
+
+    // build partial tx, payment cell capacity stays the same, and a friend may buy packs in the same
+    // transaction without signing
+    let mut inputs = vec![(build_payment_data(0, 0), 1000, composer_args.clone(), Some(user_args.clone()))];
+    let mut outputs = vec![(new_data, 1000, composer_args.clone(), Some(user_args.clone()))];
+    if let Some(friend_data) = friend_data_opt {
+        let friend_args = Bytes::from(vec![7u8; 20]);
+        inputs.push((build_payment_data(0, 0), 1000, composer_args.clone(), Some(friend_args.clone())));
+        outputs.push((friend_data, 1000, composer_args.clone(), Some(friend_args)));
+    }
+    let tx = build_partial_tx(
+        &mut context,
+        inputs,
+        outputs,
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // credit sUDT to composer's wallet
+    let sudt_out_point = context.create_cell(
+        build_output(&1000, wallet_script.clone(), Some(sudt_script.clone())),
+        Bytes::from(old_amount.to_le_bytes().to_vec()),
+    );
+    let tx = tx
+        .as_advanced_builder()
+        .input(CellInput::new_builder().previous_output(sudt_out_point).build())
+        .output(build_output(&1000, wallet_script, Some(sudt_script)))
+        .output_data(Bytes::from(new_amount.to_le_bytes().to_vec()).pack())
+        .witness(Bytes::new().pack())
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_purchase_nft_package_in_sudt() {
+    let cycles = verify_sudt_purchase(build_payment_data(2, 0), 50, 1000, 1100, None)
+        .expect("pass test_success_purchase_nft_package_in_sudt");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_in_sudt_underpaid() {
+    let result = verify_sudt_purchase(build_payment_data(2, 0), 50, 1000, 1060, None);
+    assert_script_error!(result, Payment, InsufficientSUDTAmount);
+}
+
+// each purchase is owed its own tokens, so two of them can't share a single credit
+#[test]
+fn test_fail_batch_purchase_nft_package_in_sudt_underpaid() {
+    let result = verify_sudt_purchase(build_payment_data(2, 0), 50, 1000, 1100, Some(build_payment_data(2, 0)));
+    assert_script_error!(result, Payment, InsufficientSUDTAmount);
+}
+
+#[test]
+fn test_success_batch_purchase_nft_package_in_sudt() {
+    let cycles = verify_sudt_purchase(build_payment_data(2, 0), 50, 1000, 1200, Some(build_payment_data(2, 0)))
+        .expect("pass test_success_batch_purchase_nft_package_in_sudt");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_in_sudt_into_refundable_data() {
+    let result = verify_sudt_purchase(build_refundable_payment_data(2, 0, 0), 50, 1000, 1100, None);
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+#[test]
+fn test_success_purchase_refundable_nft_package() {
+    let cycles = verify_payment_data_update(build_refundable_payment_data(0, 0, 0), 1000, build_refundable_payment_data(3, 0, 300), 1300)
+        .expect("pass test_success_purchase_refundable_nft_package");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_refundable_nft_package_with_wrong_paid() {
+    let result = verify_payment_data_update(build_refundable_payment_data(0, 0, 0), 1000, build_refundable_payment_data(3, 0, 500), 1300);
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+fn verify_refund(
+    old_data: Bytes, old_capacity: u64, new_data: Bytes, new_capacity: u64, since: u64, nft_data_opt: Option<Bytes>
+) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // build partial tx with composer nft config, whose draw rules usually have changed since the purchase
+    let deps = match nft_data_opt {
+        Some(nft_data) => vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))],
+        None => vec![]
+    };
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(old_data, old_capacity, composer_args.clone(), Some(user_args.clone()))],
+        vec![(new_data, new_capacity, composer_args.clone(), Some(user_args.clone()))],
+        deps
+    );
+
+    // lock payment input with an absolute block number since
+    let input = tx.inputs().get(0).unwrap();
+    let tx = tx
+        .as_advanced_builder()
+        .set_inputs(vec![input.as_builder().since(since.pack()).build()])
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+// starter series whose drop rates were changed after the packs had been bought
+fn changed_starter_series() -> Bytes {
+    build_nft_config(0, 100, 5, REVEAL_DELAY, wrong_nfts())
+}
+
+fn verify_snapshot_refund(since: u64, new_capacity: u64, nft_data_opt: Option<Bytes>) -> Result<Cycle, Error> {
+    let config_hash = build_config_hash(&starter_series());
+    verify_refund(
+        build_snapshot_payment_data(3, 300, 3, config_hash), 1300,
+        build_snapshot_payment_data(0, 0, 3, config_hash), new_capacity,
+        since, nft_data_opt
+    )
+}
+
+#[test]
+fn test_success_refund_unrevealed_nft_package() {
+    let since = PURCHASE_BLOCK + REFUND_DELAY_BLOCKS;
+    let cycles = verify_snapshot_refund(since, 1000, Some(changed_starter_series()))
+        .expect("pass test_success_refund_unrevealed_nft_package");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_refund_unrevealed_nft_package_too_early() {
+    let since = PURCHASE_BLOCK + REFUND_DELAY_BLOCKS - 1;
+    let result = verify_snapshot_refund(since, 1000, Some(changed_starter_series()));
+    assert_script_error!(result, Payment, RefundNotMatured);
+}
+
+#[test]
+fn test_fail_refund_unrevealed_nft_package_with_relative_since() {
+    let since = SINCE_RELATIVE_BLOCK_NUMBER_FLAG | REFUND_DELAY_BLOCKS;
+    let result = verify_snapshot_refund(since, 1000, Some(changed_starter_series()));
+    assert_script_error!(result, Payment, RefundNotMatured);
+}
+
+// the buyer already knows how the packs draw, so a refund would let them keep only the good ones
+#[test]
+fn test_fail_refund_revealable_nft_package() {
+    let since = PURCHASE_BLOCK + REFUND_DELAY_BLOCKS;
+    let result = verify_snapshot_refund(since, 1000, Some(starter_series()));
+    assert_script_error!(result, Payment, RefundNotMatured);
+}
+
+#[test]
+fn test_fail_refund_nft_package_without_config() {
+    let since = PURCHASE_BLOCK + REFUND_DELAY_BLOCKS;
+    let result = verify_snapshot_refund(since, 1000, None);
+    assert_script_error!(result, Payment, MissingCells);
+}
+
+#[test]
+fn test_fail_refund_nft_package_without_draw_rules_snapshot() {
+    let since = PURCHASE_BLOCK + REFUND_DELAY_BLOCKS;
+    let result = verify_refund(
+        build_refundable_payment_data(3, 0, 300), 1300, build_refundable_payment_data(0, 0, 0), 1000,
+        since, Some(changed_starter_series())
+    );
+    assert_script_error!(result, Payment, RefundNotMatured);
+}
+
+// the payment cell has been moved by a withdrawal since, which leaves the recorded purchase block in place
+#[test]
+fn test_success_refund_nft_package_from_recorded_purchase_block() {
+    let purchase_block = PURCHASE_BLOCK - 500;
+    let since = purchase_block + REFUND_DELAY_BLOCKS;
+    let cycles = verify_refund(
+        build_anchored_payment_data(3, 300, 3, purchase_block), 1300, build_anchored_payment_data(0, 0, 3, 0), 1000,
+        since, Some(changed_starter_series())
+    ).expect("pass test_success_refund_nft_package_from_recorded_purchase_block");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_refund_nft_package_keeping_recorded_purchase_block() {
+    let purchase_block = PURCHASE_BLOCK - 500;
+    let since = purchase_block + REFUND_DELAY_BLOCKS;
+    let result = verify_refund(
+        build_anchored_payment_data(3, 300, 3, purchase_block), 1300, build_anchored_payment_data(0, 0, 3, purchase_block), 1000,
+        since, Some(changed_starter_series())
+    );
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+#[test]
+fn test_fail_refund_nft_package_keeping_draw_offset() {
+    let config_hash = build_config_hash(&starter_series());
+    let since = PURCHASE_BLOCK + REFUND_DELAY_BLOCKS;
+    let new_payment = PaymentData {
+        version: PAYMENT_DATA_VERSION_PARTIAL, lifetime_count: 3, config_hash, draw_offset: 5, ..Default::default()
+    };
+    let result = verify_refund(
+        build_anchored_payment_data(3, 300, 3, 0), 1300, Bytes::from(new_payment.encode()), 1000,
+        since, Some(changed_starter_series())
+    );
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+#[test]
+fn test_fail_refund_more_than_paid() {
+    let since = PURCHASE_BLOCK + REFUND_DELAY_BLOCKS;
+    let result = verify_snapshot_refund(since, 900, Some(changed_starter_series()));
+    assert_script_error!(result, Payment, RefundOutOfBound);
+}
+
+fn draw_nft(config: &Vec<([u8; 20], u32)>, candidates: &Vec<usize>, lottery: u64) -> usize {
+    let total_weight = candidates.iter().map(|&i| config[i].1 as u64).sum::<u64>();
+    let lottery = lottery % total_weight;
+    let mut weight_sum = 0u64;
+    for &i in candidates.iter() {
+        weight_sum += config[i].1 as u64;
+        if lottery < weight_sum {
+            return i;
+        }
+    }
+    unreachable!()
+}
+
+fn build_pity_nft_collection(
+    config: Vec<([u8; 20], u32)>, pack_count: usize, pack_size: usize, seed: [u8; 32],
+    tier_nfts: &Vec<usize>, interval: u32, mut packs_since_hit: u32
+) -> (Bytes, u32) {
+    let mut lotteries = seed.to_vec();
+    let all_nfts = (0..config.len()).collect::<Vec<usize>>();
+    let mut collection: Vec<u8> = vec![];
+    let mut pack_hit = false;
+    for i in 0..pack_count * pack_size {
+        while (i + 1) * LOTTERY_SIZE > lotteries.len() {
+            let next_hash = blake2b_256(lotteries.clone());
+            lotteries.append(&mut next_hash.to_vec());
+        }
+        let lottery_bytes: [u8; LOTTERY_SIZE] = lotteries[i * LOTTERY_SIZE..(i + 1) * LOTTERY_SIZE].try_into().unwrap();
+        let lottery = u64::from_le_bytes(lottery_bytes);
+        let last_slot = (i + 1) % pack_size == 0;
+        let index = if last_slot && !pack_hit && packs_since_hit + 1 >= interval {
+            draw_nft(&config, tier_nfts, lottery)
+        } else {
+            draw_nft(&config, &all_nfts, lottery)
+        };
+        pack_hit = pack_hit || tier_nfts.contains(&index);
+        if last_slot {
+            packs_since_hit = if pack_hit { 0 } else { packs_since_hit + 1 };
+            pack_hit = false;
+        }
+        collection.append(&mut config[index].0.to_vec());
+    }
+    (Bytes::from(collection), packs_since_hit)
+}
+
+fn verify_pity_reveal(interval: u32, old_packs_since_hit: u32, honour_pity: bool, counter_offset: u32) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // prepare composer nft config which guarantees the legendary nft
+    let pity_rule = PityRule { tier: 1, interval, nft_indices: vec![0] };
+    let nft_data = append_nft_config_extension(
+        build_nft_config(0, 100, 5, REVEAL_DELAY, legendary_nfts()),
+        EXTENSION_PITY_RULE,
+        pity_rule.encode()
+    );
+
+    // build partial tx
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_pity_payment_data(2, 0, 0, old_packs_since_hit), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(build_pity_payment_data(0, 0, 0, 0), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // draw the expected collection, pity rule applied or not
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let payment_script_hash = tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+    let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+    let pity_interval = if honour_pity { interval } else { u32::MAX };
+    let (nft_collection, packs_since_hit) = build_pity_nft_collection(
+        legendary_nfts(), 2, 5, seed, &pity_rule.nft_indices, pity_interval, old_packs_since_hit
+    );
+
+    // record the resulting pity counter
+    let payment_data = build_pity_payment_data(0, 0, 0, packs_since_hit + counter_offset);
+    let tx = tx
+        .as_advanced_builder()
+        .set_outputs_data(vec![payment_data.pack()])
+        .build();
+
+    // append nft contract output
+    let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&always_success_out_point, user_args)
+        .expect("build nft lock_script");
+    let type_script = context
+        .build_script(&always_success_out_point, lock_hash.raw_data())
+        .expect("build nft type_script");
+    let tx = tx
+        .as_advanced_builder()
+        .output(build_output(&100, lock_script, Some(type_script)))
+        .output_data(nft_collection.pack())
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_reveal_nft_package_with_pity_guarantee() {
+    let cycles = verify_pity_reveal(1, 0, true, 0)
+        .expect("pass test_success_reveal_nft_package_with_pity_guarantee");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_success_reveal_nft_package_with_pity_counter_running() {
+    let cycles = verify_pity_reveal(10, 3, true, 0)
+        .expect("pass test_success_reveal_nft_package_with_pity_counter_running");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_reveal_nft_package_ignoring_pity_guarantee() {
+    let result = verify_pity_reveal(1, 0, false, 0);
+    assert_script_error!(result, Payment, InvalidRevealNFTData);
+}
+
+#[test]
+fn test_fail_reveal_nft_package_with_wrong_pity_counter() {
+    let result = verify_pity_reveal(10, 3, true, 1);
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+fn verify_limited_sale(old_stock: u32, new_stock: u32, with_sale_cell: bool) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // prepare composer nft config of a limited-edition series
+    let nft_data = append_nft_config_extension(starter_series(), EXTENSION_LIMITED_SALE, vec![]);
+    let sale_args = SaleArgs { composer_pubkey_hash: composer_args.to_vec().try_into().unwrap(), series_id: 0 };
+    let sale_args = Bytes::from(sale_args.encode());
+
+    // build partial tx which buys 2 packs
+    let mut inputs = vec![(build_payment_data(0, 0), 1000, composer_args.clone(), Some(user_args.clone()))];
+    let mut outputs = vec![(build_payment_data(2, 0), 1200, composer_args.clone(), Some(user_args.clone()))];
+    if with_sale_cell {
+        inputs.push((Bytes::from(encode_sale_stock(old_stock)), 100, composer_args.clone(), Some(sale_args.clone())));
+        outputs.push((Bytes::from(encode_sale_stock(new_stock)), 100, composer_args.clone(), Some(sale_args)));
+    }
+    let tx = build_partial_tx(
+        &mut context,
+        inputs,
+        outputs,
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_purchase_limited_edition_nft_package() {
+    let cycles = verify_limited_sale(10000, 9998, true)
+        .expect("pass test_success_purchase_limited_edition_nft_package");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_limited_edition_nft_package_without_sale_cell() {
+    let result = verify_limited_sale(10000, 10000, false);
+    assert_script_error!(result, Payment, MissingSaleCell);
+}
+
+#[test]
+fn test_fail_purchase_limited_edition_nft_package_with_wrong_stock() {
+    let result = verify_limited_sale(10000, 9999, true);
+    assert_script_error!(result, Payment, InvalidSaleData);
+}
+
+#[test]
+fn test_fail_purchase_sold_out_nft_package() {
+    let result = verify_limited_sale(1, 0, true);
+    assert_script_error!(result, Payment, SoldOut);
+}
+
+fn scheduled_series(start_block: u64, end_block: u64, paused: bool) -> Bytes {
+    let sale_window = SaleWindow { start_block, end_block, paused };
+    append_nft_config_extension(starter_series(), EXTENSION_SALE_WINDOW, sale_window.encode())
+}
+
+fn verify_sale_window_purchase(start_block: u64, end_block: u64, paused: bool, since: u64) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // prepare composer nft config with a scheduled sale
+    let nft_data = scheduled_series(start_block, end_block, paused);
+
+    // build partial tx
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_payment_data(0, 0), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(build_payment_data(2, 0), 1200, composer_args.clone(), Some(user_args.clone()))],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // lock payment input with an absolute block number since
+    let input = tx.inputs().get(0).unwrap();
+    let tx = tx
+        .as_advanced_builder()
+        .set_inputs(vec![input.as_builder().since(since.pack()).build()])
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_purchase_nft_package_in_sale_window() {
+    let cycles = verify_sale_window_purchase(100, 200, false, 150)
+        .expect("pass test_success_purchase_nft_package_in_sale_window");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_before_sale_window() {
+    let result = verify_sale_window_purchase(100, 200, false, 50);
+    assert_script_error!(result, Payment, SaleWindowClosed);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_with_relative_since() {
+    let result = verify_sale_window_purchase(100, 200, false, SINCE_RELATIVE_BLOCK_NUMBER_FLAG | 150);
+    assert_script_error!(result, Payment, SaleWindowClosed);
+}
+
+// `since` can't bound a purchase from above, so the window is only checked at purchase and a late
+// purchase is revealed as usual
+#[test]
+fn test_success_reveal_nft_package_bought_after_sale_window() {
+    let cycles = verify_series_operation(
+        build_payment_data(2, 0), 1200, build_payment_data(0, 0), 1200,
+        vec![scheduled_series(0, PURCHASE_BLOCK - 1, false)], Some((right_nfts(), 2 * 5))
+    ).expect("pass test_success_reveal_nft_package_bought_after_sale_window");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_paused_nft_package() {
+    let result = verify_sale_window_purchase(100, 200, true, 150);
+    assert_script_error!(result, Payment, SalePaused);
+}
+
+fn limited_per_buyer_series(max_packs_per_buyer: u32) -> Bytes {
+    append_nft_config_extension(starter_series(), EXTENSION_PURCHASE_LIMIT, max_packs_per_buyer.to_le_bytes().to_vec())
+}
+
+#[test]
+fn test_success_purchase_nft_package_within_buyer_limit() {
+    let cycles = verify_payment_data_update_with_config(
+        limited_per_buyer_series(10),
+        build_registered_payment_data(0, 0, 8, true), 1000,
+        build_registered_payment_data(2, 200, 10, true), 1200
+    ).expect("pass test_success_purchase_nft_package_within_buyer_limit");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_beyond_buyer_limit() {
+    let result = verify_payment_data_update_with_config(
+        limited_per_buyer_series(10),
+        build_registered_payment_data(0, 0, 9, true), 1000,
+        build_registered_payment_data(2, 200, 11, true), 1200
+    );
+    assert_script_error!(result, Payment, PurchaseLimitExceeded);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_with_stale_lifetime_count() {
+    let result = verify_payment_data_update_with_config(
+        limited_per_buyer_series(10),
+        build_registered_payment_data(0, 0, 9, true), 1000,
+        build_registered_payment_data(2, 200, 9, true), 1200
+    );
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+// a buyer past the limit could otherwise keep buying into another payment cell of their own
+#[test]
+fn test_fail_purchase_nft_package_beyond_buyer_limit_into_unregistered_cell() {
+    let result = verify_payment_data_update_with_config(
+        limited_per_buyer_series(10),
+        build_registered_payment_data(0, 0, 0, false), 1000,
+        build_registered_payment_data(2, 200, 2, false), 1200
+    );
+    assert_script_error!(result, Payment, UnregisteredPaymentCell);
+}
+
+#[test]
+fn test_fail_purchase_limited_nft_package_with_lifetime_payment_data() {
+    let result = verify_payment_data_update_with_config(
+        limited_per_buyer_series(10),
+        build_lifetime_payment_data(0, 0, 0, 0, 8), 1000,
+        build_lifetime_payment_data(2, 0, 200, 0, 10), 1200
+    );
+    assert_script_error!(result, Payment, UnregisteredPaymentCell);
+}
+
+#[test]
+fn test_fail_register_payment_cell_by_purchase() {
+    let result = verify_payment_data_update_with_config(
+        starter_series(),
+        build_registered_payment_data(0, 0, 0, false), 1000,
+        build_registered_payment_data(2, 200, 2, true), 1200
+    );
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+#[test]
+fn test_fail_purchase_limited_nft_package_without_lifetime_count() {
+    let result = verify_payment_data_update_with_config(
+        limited_per_buyer_series(10),
+        build_payment_data(0, 0), 1000,
+        build_payment_data(2, 0), 1200
+    );
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+fn hash_sorted_pair(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let mut pair = vec![];
+    if a <= b {
+        pair.extend_from_slice(&a);
+        pair.extend_from_slice(&b);
+    } else {
+        pair.extend_from_slice(&b);
+        pair.extend_from_slice(&a);
+    }
+    blake2b_256(pair)
+}
+
+fn verify_whitelist_purchase(new_capacity: u64, with_proof: bool, unlock_backer: bool) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // prepare whitelist of four backer locks, the first of which belongs to the buyer
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let backer_locks = (0u8..4)
+        .map(|i| context
+            .build_script(&always_success_out_point, Bytes::from(vec![i]))
+            .expect("build backer lock_script"))
+        .collect::<Vec<Script>>();
+    let leaves = backer_locks
+        .iter()
+        .map(|lock| blake2b_256(lock.calc_script_hash().raw_data()))
+        .collect::<Vec<[u8; 32]>>();
+    let node_23 = hash_sorted_pair(leaves[2], leaves[3]);
+    let merkle_root = hash_sorted_pair(hash_sorted_pair(leaves[0], leaves[1]), node_23);
+
+    // prepare composer nft config with presale price
+    let mut whitelist = merkle_root.to_vec();
+    whitelist.append(&mut 60u64.to_le_bytes().to_vec());
+    let nft_data = append_nft_config_extension(starter_series(), EXTENSION_WHITELIST, whitelist);
+
+    // build partial tx which buys 2 packs
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_payment_data(0, 0), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(build_payment_data(2, 0), new_capacity, composer_args.clone(), Some(user_args.clone()))],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // unlock a cell of buyer's backer lock
+    let tx = if unlock_backer {
+        let backer_out_point = context.create_cell(build_output(&100, backer_locks[0].clone(), None), Bytes::new());
+        tx.as_advanced_builder()
+            .input(CellInput::new_builder().previous_output(backer_out_point).build())
+            .output(build_output(&100, backer_locks[0].clone(), None))
+            .output_data(Bytes::new().pack())
+            .witness(Bytes::new().pack())
+            .build()
+    } else {
+        tx
+    };
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let mut witness = WitnessArgs::new_builder();
+    if with_proof {
+        let mut proof = backer_locks[0].calc_script_hash().raw_data().to_vec();
+        proof.append(&mut leaves[1].to_vec());
+        proof.append(&mut node_23.to_vec());
+        witness = witness.input_type(Some(Bytes::from(proof)).pack());
+    }
+    let tx = sign_tx_with_witness_args(tx, &user_privkey, witness.build());
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_purchase_nft_package_at_presale_price() {
+    let cycles = verify_whitelist_purchase(1120, true, true)
+        .expect("pass test_success_purchase_nft_package_at_presale_price");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_at_presale_price_without_proof() {
+    let result = verify_whitelist_purchase(1120, false, true);
+    assert_script_error!(result, Payment, InsufficientCapacity);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_at_presale_price_without_backer_lock() {
+    let result = verify_whitelist_purchase(1120, true, false);
+    assert_script_error!(result, Payment, InvalidWhitelistProof);
+}
+
+#[test]
+fn test_success_purchase_whitelisted_nft_package_at_full_price() {
+    let cycles = verify_whitelist_purchase(1200, false, false)
+        .expect("pass test_success_purchase_whitelisted_nft_package_at_full_price");
+    println!("consume cycles: {}", cycles);
+}
+
+fn verify_gift(old_data: Bytes, old_capacity: u64, new_data: Bytes, new_capacity: u64) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create friend keypair who sends the gift
+    let keypair_friend = Generator::random_keypair();
+    let friend_privkey = keypair_friend.0;
+
+    // prepare composer nft config
+    let nft_data = starter_series();
+
+    // build partial tx
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(old_data, old_capacity, composer_args.clone(), Some(user_args.clone()))],
+        vec![(new_data, new_capacity, composer_args.clone(), Some(user_args.clone()))],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &friend_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_gift_nft_package() {
+    let cycles = verify_gift(build_payment_data(0, 0), 1000, build_payment_data(2, 0), 1200)
+        .expect("pass test_success_gift_nft_package");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_reveal_gifted_nft_package_by_friend() {
+    let result = verify_gift(build_payment_data(3, 0), 1300, build_payment_data(0, 0), 1300);
+    assert_script_error!(result, Payment, InvalidGiftOperation);
+}
+
+#[test]
+fn test_fail_refund_gifted_nft_package_by_friend() {
+    let result = verify_gift(build_refundable_payment_data(3, 0, 300), 1300, build_refundable_payment_data(0, 0, 0), 1000);
+    assert_script_error!(result, Payment, InvalidGiftOperation);
+}
+
+fn verify_batch_purchase(
+    second_old_data: Bytes, second_old_capacity: u64, second_new_data: Bytes, second_new_capacity: u64
+) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+    let composer = Wallet::random();
+    let first_buyer = Wallet::random();
+    let second_buyer = Wallet::random();
+
+    // build partial tx which is assembled by a storefront for both buyers
+    let tx = build_partial_tx(
+        &mut context,
+        vec![
+            (build_payment_data(0, 0), 1000, composer.args.clone(), Some(first_buyer.args.clone())),
+            (second_old_data, second_old_capacity, composer.args.clone(), Some(second_buyer.args.clone()))
+        ],
+        vec![
+            (build_payment_data(2, 0), 1200, composer.args.clone(), Some(first_buyer.args.clone())),
+            (second_new_data, second_new_capacity, composer.args.clone(), Some(second_buyer.args.clone()))
+        ],
+        vec![(starter_series(), 0, composer.args.clone(), Some(composer.args.clone()))]
+    );
+
+    // each buyer signs the witness of their own payment input
+    sign_and_verify(&mut context, tx, vec![(&first_buyer, 0), (&second_buyer, 1)])
+}
+
+#[test]
+fn test_success_batch_purchase_for_many_buyers() {
+    let cycles = verify_batch_purchase(build_payment_data(0, 0), 1000, build_payment_data(1, 0), 1100)
+        .expect("pass test_success_batch_purchase_for_many_buyers");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_batch_purchase_with_underpaid_buyer() {
+    let result = verify_batch_purchase(build_payment_data(0, 0), 1000, build_payment_data(2, 0), 1100);
+    assert_script_error!(result, Payment, InsufficientCapacity);
+}
+
+#[test]
+fn test_fail_batch_decrease_pack_counter_of_second_buyer() {
+    // a decreased counter can only be a reveal, which has no reveal header here
+    let result = verify_batch_purchase(build_payment_data(1, 0), 1100, build_payment_data(0, 0), 1100);
+    assert_script_error!(result, Payment, MissingRevealHeader);
+}
+
+// both buyers reveal two packs, the nfts of the second buyer may be sent to the first one instead
+fn verify_batch_reveal(second_nfts_to_first_buyer: bool) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+    let composer = Wallet::random();
+    let first_buyer = Wallet::random();
+    let second_buyer = Wallet::random();
+
+    // build partial tx which is assembled by a storefront for both buyers
+    let tx = build_partial_tx(
+        &mut context,
+        vec![
+            (build_payment_data(2, 0), 1200, composer.args.clone(), Some(first_buyer.args.clone())),
+            (build_payment_data(2, 0), 1200, composer.args.clone(), Some(second_buyer.args.clone()))
+        ],
+        vec![
+            (build_payment_data(0, 0), 1200, composer.args.clone(), Some(first_buyer.args.clone())),
+            (build_payment_data(0, 0), 1200, composer.args.clone(), Some(second_buyer.args.clone()))
+        ],
+        vec![(starter_series(), 0, composer.args.clone(), Some(composer.args.clone()))]
+    );
+
+    // each buyer draws from the lottery salted by their own payment script
+    let (mut tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
+    for (index, buyer) in [&first_buyer, &second_buyer].iter().enumerate() {
+        let payment_script_hash = tx.output(index).unwrap().type_().to_opt().unwrap().calc_script_hash();
+        let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+        let owner = if second_nfts_to_first_buyer { &first_buyer } else { *buyer };
+        tx = append_nft_output(&mut context, tx, owner, lock_hash.raw_data(), build_nft_collection(right_nfts(), 2 * 5, seed));
+    }
+
+    sign_and_verify(&mut context, tx, vec![(&first_buyer, 0), (&second_buyer, 1)])
+}
+
+#[test]
+fn test_success_batch_reveal_for_many_buyers() {
+    let cycles = verify_batch_reveal(false)
+        .expect("pass test_success_batch_reveal_for_many_buyers");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_batch_reveal_into_nfts_of_another_buyer() {
+    let result = verify_batch_reveal(true);
+    assert_script_error!(result, Payment, MissingCells);
+}
+
+#[test]
+fn test_fail_create_wallets_with_nonzero_counter_after_valid_creation() {
+    let mut context = Context::default();
+
+    // create two composer keypairs
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let keypair_another = Generator::random_keypair();
+    let compressed_pubkey = keypair_another.1.serialize();
+    let another_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // build partial tx which creates a valid wallet followed by a prefilled one
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(Bytes::new(), 2000, user_args.clone(), None)],
+        vec![
+            (build_payment_data(0, 0), 1000, composer_args.clone(), Some(user_args.clone())),
+            (build_payment_data(5, 0), 1000, another_args.clone(), Some(user_args.clone()))
+        ],
+        vec![]
+    );
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    let result = context.verify_tx(&tx, MAX_CYCLES);
+    assert_script_error!(result, Payment, InvalidWalletCreationFormat);
+}
+
+fn build_ownerlock_hash(context: &mut Context, composer_args: &Bytes) -> Bytes {
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let ownerlock_script = context
+        .build_script(&always_success_out_point, composer_args.clone())
+        .expect("build ownerlock");
+    ownerlock_script.calc_script_hash().raw_data()
+}
+
+fn verify_sealed_pack_mint(new_capacity: u64, count: u32, draw_hash: [u8; 32]) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // build partial tx which mints a sealed pack instead of increasing the pack counter
+    let ownerlock_hash = build_ownerlock_hash(&mut context, &composer_args);
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_payment_data(0, 0), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![
+            (build_payment_data(0, 0), new_capacity, composer_args.clone(), Some(user_args.clone())),
+            (Bytes::new(), 500, user_args.clone(), Some(ownerlock_hash))
+        ],
+        vec![(starter_series(), 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // sealed pack id is derived from the first input and its output index
+    let mut pack_id_source = tx.inputs().get(0).unwrap().previous_output().as_slice().to_vec();
+    pack_id_source.append(&mut 1u64.to_le_bytes().to_vec());
+    let sealed_pack_data = build_sealed_pack_data_with_rules(0, count, 0, blake2b_256(pack_id_source), draw_hash);
+    let payment_data = tx.outputs_data().get(0).unwrap();
+    let tx = tx
+        .as_advanced_builder()
+        .set_outputs_data(vec![payment_data, sealed_pack_data.pack()])
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_mint_sealed_nft_package() {
+    let cycles = verify_sealed_pack_mint(1200, 2, build_config_hash(&starter_series()))
+        .expect("pass test_success_mint_sealed_nft_package");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_mint_sealed_nft_package_underpaid() {
+    let result = verify_sealed_pack_mint(1100, 2, build_config_hash(&starter_series()));
+    assert_script_error!(result, Payment, InsufficientCapacity);
+}
+
+#[test]
+fn test_fail_mint_sealed_nft_package_with_other_draw_rules() {
+    let result = verify_sealed_pack_mint(1200, 2, build_config_hash(&changed_starter_series()));
+    assert_script_error!(result, Payment, ConfigMismatch);
+}
+
+// the composer's withdrawal skips the purchase checks, so it can't carry freshly minted packs
+#[test]
+fn test_fail_mint_sealed_nft_package_by_composer_withdrawal() {
+    let mut context = Context::default();
+    let composer = Wallet::random();
+    let user = Wallet::random();
+
+    // build partial tx which mints a sealed pack while withdrawing from the buyer's payment cell
+    let ownerlock_hash = build_ownerlock_hash(&mut context, &composer.args);
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_payment_data(0, 0), 1000, composer.args.clone(), Some(user.args.clone()))],
+        vec![
+            (build_payment_data(0, 0), 500, composer.args.clone(), Some(user.args.clone())),
+            (Bytes::new(), 500, composer.args.clone(), Some(ownerlock_hash))
+        ],
+        vec![(starter_series(), 0, composer.args.clone(), Some(composer.args.clone()))]
+    );
+
+    // sealed pack id is derived from the first input and its output index
+    let mut pack_id_source = tx.inputs().get(0).unwrap().previous_output().as_slice().to_vec();
+    pack_id_source.append(&mut 1u64.to_le_bytes().to_vec());
+    let sealed_pack_data = build_sealed_pack_data(0, 2, 0, blake2b_256(pack_id_source));
+    let payment_data = tx.outputs_data().get(0).unwrap();
+    let tx = tx
+        .as_advanced_builder()
+        .set_outputs_data(vec![payment_data, sealed_pack_data.pack()])
+        .build();
+
+    // run
+    let result = sign_and_verify(&mut context, tx, vec![(&composer, 0)]);
+    assert_script_error!(result, Payment, InvalidSealedPackData);
+}
+
+fn verify_sealed_pack_transfer(new_count: u32) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // transfer the sealed pack to a friend, recording the block it was minted in
+    let friend_args = Bytes::from(vec![7u8; 20]);
+    let ownerlock_hash = build_ownerlock_hash(&mut context, &composer_args);
+    let pack_id = blake2b_256(b"sealed pack");
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_sealed_pack_data(0, 2, 0, pack_id), 500, user_args.clone(), Some(ownerlock_hash.clone()))],
+        vec![(build_sealed_pack_data(0, new_count, PURCHASE_BLOCK, pack_id), 500, friend_args, Some(ownerlock_hash))],
+        vec![]
+    );
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_transfer_sealed_nft_package() {
+    let cycles = verify_sealed_pack_transfer(2)
+        .expect("pass test_success_transfer_sealed_nft_package");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_split_sealed_nft_package() {
+    let result = verify_sealed_pack_transfer(1);
+    assert_script_error!(result, Payment, InvalidSealedPackData);
+}
+
+fn verify_sealed_pack_reveal(nft_data: Bytes, revealed_nfts: Vec<([u8; 20], u32)>) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // build partial tx which consumes the sealed pack
+    let ownerlock_hash = build_ownerlock_hash(&mut context, &composer_args);
+    let pack_id = blake2b_256(b"sealed pack");
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_sealed_pack_data(0, 2, 0, pack_id), 500, user_args.clone(), Some(ownerlock_hash.clone()))],
+        vec![],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // pin reveal header REVEAL_DELAY blocks after the mint and draw the collection with the pack id
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let seed = build_lottery_seed(&reveal_header, &Byte32::new(pack_id));
+    let nft_collection = build_nft_collection(revealed_nfts, 10, seed);
+
+    // append nft contract output
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&always_success_out_point, user_args)
+        .expect("build nft lock_script");
+    let type_script = context
+        .build_script(&always_success_out_point, ownerlock_hash)
+        .expect("build nft type_script");
+    let tx = tx
+        .as_advanced_builder()
+        .output(build_output(&100, lock_script, Some(type_script)))
+        .output_data(nft_collection.pack())
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_reveal_sealed_nft_package() {
+    let cycles = verify_sealed_pack_reveal(starter_series(), right_nfts())
+        .expect("pass test_success_reveal_sealed_nft_package");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_reveal_sealed_nft_package_with_wrong_nfts() {
+    let result = verify_sealed_pack_reveal(starter_series(), wrong_nfts());
+    assert_script_error!(result, Payment, InvalidRevealNFTData);
+}
+
+// the composer edits the weights of the series after the pack was minted
+#[test]
+fn test_fail_reveal_sealed_nft_package_with_edited_draw_rules() {
+    let result = verify_sealed_pack_reveal(changed_starter_series(), right_nfts());
+    assert_script_error!(result, Payment, ConfigMismatch);
+}
+
+fn verify_reveal_into_collection(tamper_collection: bool) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // build partial tx
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_payment_data(1, 0), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(build_payment_data(0, 0), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(starter_series(), 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // pin reveal header REVEAL_DELAY blocks after the purchase and draw the expected collection
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let payment_script_hash = tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+    let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+    let nft_collection = build_nft_collection(right_nfts(), 5, seed);
+
+    // append revealed nfts to the collection cell the user already owns
+    let mut owned_collection = right_nfts()[0].0.to_vec();
+    owned_collection.append(&mut right_nfts()[1].0.to_vec());
+    let mut grown_collection = if tamper_collection {
+        right_nfts()[2].0.repeat(2)
+    } else {
+        owned_collection.clone()
+    };
+    grown_collection.append(&mut nft_collection.to_vec());
+
+    // replace nft contract cell
+    let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&always_success_out_point, user_args)
+        .expect("build nft lock_script");
+    let type_script = context
+        .build_script(&always_success_out_point, lock_hash.raw_data())
+        .expect("build nft type_script");
+    let collection_out_point = context.create_cell(
+        build_output(&100, lock_script.clone(), Some(type_script.clone())),
+        Bytes::from(owned_collection),
+    );
+    let tx = tx
+        .as_advanced_builder()
+        .input(CellInput::new_builder().previous_output(collection_out_point).build())
+        .output(build_output(&200, lock_script, Some(type_script)))
+        .output_data(Bytes::from(grown_collection).pack())
+        .witness(Bytes::new().pack())
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_reveal_nft_package_into_owned_collection() {
+    let cycles = verify_reveal_into_collection(false)
+        .expect("pass test_success_reveal_nft_package_into_owned_collection");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_reveal_nft_package_rewriting_owned_collection() {
+    let result = verify_reveal_into_collection(true);
+    assert_script_error!(result, Payment, InvalidRevealNFTData);
+}
+
+fn verify_reveal_with_nft_type(nft_code_hash: &[u8], nft_hash_type: u8) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // prepare composer nft config which names the nft type script
+    let nft_data = build_nft_config_with_type(0, 100, 5, REVEAL_DELAY, nft_code_hash, nft_hash_type, right_nfts());
+
+    // build partial tx
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_payment_data(1, 0), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(build_payment_data(0, 0), 1000, composer_args.clone(), Some(user_args.clone()))],
+        vec![(nft_data, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // pin reveal header REVEAL_DELAY blocks after the purchase and draw the expected collection
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let payment_script_hash = tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+    let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+    let nft_collection = build_nft_collection(right_nfts(), 5, seed);
+
+    // append nft output typed by always-success script
+    let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&always_success_out_point, user_args)
+        .expect("build nft lock_script");
+    let type_script = context
+        .build_script(&always_success_out_point, lock_hash.raw_data())
+        .expect("build nft type_script");
+    let tx = tx
+        .as_advanced_builder()
+        .output(build_output(&100, lock_script, Some(type_script)))
+        .output_data(nft_collection.pack())
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_reveal_nft_package_into_configured_nft_type() {
+    let nft_code_hash = CellOutput::calc_data_hash(&ALWAYS_SUCCESS).raw_data();
+    let cycles = verify_reveal_with_nft_type(&nft_code_hash, HASH_TYPE_DATA)
+        .expect("pass test_success_reveal_nft_package_into_configured_nft_type");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_reveal_nft_package_into_bogus_nft_code() {
+    // the nft output typed by any other script isn't counted as revealed
+    let nft_code_hash = CellOutput::calc_data_hash(&Loader::default().load_binary("nft")).raw_data();
+    let result = verify_reveal_with_nft_type(&nft_code_hash, HASH_TYPE_DATA);
+    assert_script_error!(result, Payment, MissingCells);
+}
+
+#[test]
+fn test_fail_reveal_nft_package_into_bogus_nft_hash_type() {
+    let nft_code_hash = CellOutput::calc_data_hash(&ALWAYS_SUCCESS).raw_data();
+    let result = verify_reveal_with_nft_type(&nft_code_hash, HASH_TYPE_TYPE);
+    assert_script_error!(result, Payment, MissingCells);
+}
+
+#[test]
+fn test_success_purchase_nft_package_with_config_snapshot() {
+    let config_hash = build_config_hash(&starter_series());
+    let cycles = verify_payment_data_update_with_config(
+        starter_series(),
+        build_snapshot_payment_data(0, 0, 0, [0u8; 32]), 1000,
+        build_snapshot_payment_data(2, 200, 2, config_hash), 1200
+    ).expect("pass test_success_purchase_nft_package_with_config_snapshot");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_nft_package_with_wrong_config_snapshot() {
+    let config_hash = build_config_hash(&expansion_series());
+    let result = verify_payment_data_update_with_config(
+        starter_series(),
+        build_snapshot_payment_data(0, 0, 0, [0u8; 32]), 1000,
+        build_snapshot_payment_data(2, 200, 2, config_hash), 1200
+    );
+    assert_script_error!(result, Payment, ConfigMismatch);
+}
+
+fn verify_snapshot_reveal(purchase_config: Bytes, reveal_config: Bytes) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+
+    // create composer keypair
+    let keypair_composer = Generator::random_keypair();
+    let compressed_pubkey = keypair_composer.1.serialize();
+    let composer_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+
+    // create user keypair
+    let keypair_user = Generator::random_keypair();
+    let compressed_pubkey = keypair_user.1.serialize();
+    let user_args = Bytes::from(helper::blake160(compressed_pubkey.to_vec().as_slice()).to_vec());
+    let user_privkey = keypair_user.0;
+
+    // build partial tx, packs were bought under the purchase config
+    let config_hash = build_config_hash(&purchase_config);
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_snapshot_payment_data(1, 100, 1, config_hash), 1100, composer_args.clone(), Some(user_args.clone()))],
+        vec![(build_snapshot_payment_data(0, 0, 1, config_hash), 1100, composer_args.clone(), Some(user_args.clone()))],
+        vec![(reveal_config, 0, composer_args.clone(), Some(composer_args.clone()))]
+    );
+
+    // pin reveal header REVEAL_DELAY blocks after the purchase and draw the expected collection
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let payment_script_hash = tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+    let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+    let nft_collection = build_nft_collection(right_nfts(), 5, seed);
+
+    // append nft contract output
+    let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let lock_script = context
+        .build_script(&always_success_out_point, user_args)
+        .expect("build nft lock_script");
+    let type_script = context
+        .build_script(&always_success_out_point, lock_hash.raw_data())
+        .expect("build nft type_script");
+    let tx = tx
+        .as_advanced_builder()
+        .output(build_output(&100, lock_script, Some(type_script)))
+        .output_data(nft_collection.pack())
+        .build();
+
+    // complete
+    let tx = context.complete_tx(tx);
+    let tx = sign_tx(tx, &user_privkey);
+
+    // run
+    context.verify_tx(&tx, MAX_CYCLES)
+}
+
+#[test]
+fn test_success_reveal_nft_package_with_config_snapshot() {
+    let cycles = verify_snapshot_reveal(starter_series(), starter_series())
+        .expect("pass test_success_reveal_nft_package_with_config_snapshot");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_success_reveal_nft_package_after_price_update() {
+    // same nfts, but the composer has raised the price since the purchase
+    let updated_config = build_nft_config(0, 150, 5, REVEAL_DELAY, right_nfts());
+    let cycles = verify_snapshot_reveal(starter_series(), updated_config)
+        .expect("pass test_success_reveal_nft_package_after_price_update");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_success_reveal_nft_package_after_sale_paused() {
+    let cycles = verify_snapshot_reveal(starter_series(), scheduled_series(0, u64::MAX, true))
+        .expect("pass test_success_reveal_nft_package_after_sale_paused");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_reveal_nft_package_after_weight_update() {
+    let mut nfts = right_nfts();
+    nfts[0].1 += 1;
+    let updated_config = build_nft_config(0, 100, 5, REVEAL_DELAY, nfts);
+    let result = verify_snapshot_reveal(starter_series(), updated_config);
+    assert_script_error!(result, Payment, ConfigMismatch);
+}
+
+fn build_revenue_split(recipients: Vec<(&Bytes, u16)>) -> Vec<u8> {
+    let recipients = recipients
+        .into_iter()
+        .map(|(lock_hash, share)| (lock_hash.to_vec().try_into().unwrap(), share))
+        .collect::<Vec<([u8; 32], u16)>>();
+    config::encode_revenue_split(&recipients)
+}
+
+struct RevenueSplitPurchase {
+    composer_share: u16,
+    partner_share: u16,
+    // capacity of the buyer's payment cell, which holds 1100 before buying 10 packs at 100
+    payment_capacity: u64,
+    // capacity of the cell paid to the partner wallet
+    partner_capacity: u64,
+    // whether a second buyer purchases a pack in the same transaction
+    second_buyer: bool
+}
+
+impl Default for RevenueSplitPurchase {
+    fn default() -> Self {
+        RevenueSplitPurchase {
+            composer_share: 6000,
+            partner_share: 4000,
+            payment_capacity: 1700,
+            partner_capacity: 400,
+            second_buyer: false
+        }
+    }
+}
+
+fn verify_revenue_split_purchase(purchase: RevenueSplitPurchase) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+    let composer = Wallet::random();
+    let partner = Wallet::random();
+    let buyer = Wallet::random();
+
+    // prepare composer nft config which splits revenue with the co-publishing partner wallet
+    let composer_lock_hash = build_ownerlock_hash(&mut context, &composer.args);
+    let partner_lock_hash = build_ownerlock_hash(&mut context, &partner.args);
+    let nft_data = append_nft_config_extension(
+        starter_series(),
+        EXTENSION_REVENUE_SPLIT,
+        build_revenue_split(vec![(&composer_lock_hash, purchase.composer_share), (&partner_lock_hash, purchase.partner_share)])
+    );
+
+    // build partial tx which buys 10 packs, and optionally another buyer's pack in the same transaction
+    let mut inputs = vec![(build_payment_data(0, 0), 1100, composer.args.clone(), Some(buyer.args.clone()))];
+    let mut outputs = vec![
+        (build_payment_data(10, 0), purchase.payment_capacity, composer.args.clone(), Some(buyer.args.clone())),
+        (Bytes::new(), purchase.partner_capacity, partner.args.clone(), None)
+    ];
+    if purchase.second_buyer {
+        let friend = Wallet::random();
+        inputs.push((build_payment_data(0, 0), 1000, composer.args.clone(), Some(friend.args.clone())));
+        outputs.push((build_payment_data(1, 0), 1100, composer.args.clone(), Some(friend.args.clone())));
+    }
+    let tx = build_partial_tx(
+        &mut context,
+        inputs,
+        outputs,
+        vec![(nft_data, 0, composer.args.clone(), Some(composer.args.clone()))]
+    );
+
+    sign_and_verify(&mut context, tx, vec![(&buyer, 0)])
+}
+
+#[test]
+fn test_success_purchase_with_revenue_split() {
+    let cycles = verify_revenue_split_purchase(RevenueSplitPurchase::default())
+        .expect("pass test_success_purchase_with_revenue_split");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_with_underpaid_recipient() {
+    let result = verify_revenue_split_purchase(RevenueSplitPurchase {
+        payment_capacity: 2000,
+        partner_capacity: 100,
+        ..Default::default()
+    });
+    assert_script_error!(result, Payment, InsufficientCapacity);
+}
+
+#[test]
+fn test_fail_purchase_with_shares_not_adding_up() {
+    let result = verify_revenue_split_purchase(RevenueSplitPurchase { partner_share: 3000, ..Default::default() });
+    assert_script_error!(result, Payment, InvalidNFTData);
+}
+
+#[test]
+fn test_success_batch_purchase_with_revenue_split() {
+    // the partner is owed 40% of both purchases, 400 from the first buyer and 40 from the second
+    let cycles = verify_revenue_split_purchase(RevenueSplitPurchase {
+        partner_capacity: 440,
+        second_buyer: true,
+        ..Default::default()
+    })
+    .expect("pass test_success_batch_purchase_with_revenue_split");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_batch_purchase_with_underpaid_recipient() {
+    // paying the partner its share of the first purchase only
+    let result = verify_revenue_split_purchase(RevenueSplitPurchase { second_buyer: true, ..Default::default() });
+    assert_script_error!(result, Payment, InsufficientCapacity);
+}
+
+// the composer's share of a co-published purchase and the price of another series bought in the same
+// transaction flow into the same wallet, so neither can be counted for the other
+#[test]
+fn test_fail_purchase_with_revenue_split_beside_unsplit_series() {
+    let mut context = Context::default();
+    let composer = Wallet::random();
+    let partner = Wallet::random();
+    let buyer = Wallet::random();
+    let friend = Wallet::random();
+
+    // prepare composer nft configs, only the starter series is co-published
+    let composer_lock_hash = build_ownerlock_hash(&mut context, &composer.args);
+    let partner_lock_hash = build_ownerlock_hash(&mut context, &partner.args);
+    let split_series = append_nft_config_extension(
+        starter_series(),
+        EXTENSION_REVENUE_SPLIT,
+        build_revenue_split(vec![(&composer_lock_hash, 6000), (&partner_lock_hash, 4000)])
+    );
+
+    // the buyer owes the composer 600 for 10 starter packs but pays 300, which the friend's expansion
+    // pack at 300 would make up for if it were counted twice
+    let tx = build_partial_tx(
+        &mut context,
+        vec![
+            (build_payment_data(0, 0), 1000, composer.args.clone(), Some(buyer.args.clone())),
+            (build_payment_data(0, 1), 1000, composer.args.clone(), Some(friend.args.clone()))
+        ],
+        vec![
+            (build_payment_data(10, 0), 1300, composer.args.clone(), Some(buyer.args.clone())),
+            (build_payment_data(1, 1), 1300, composer.args.clone(), Some(friend.args.clone())),
+            (Bytes::new(), 400, partner.args.clone(), None)
+        ],
+        vec![
+            (split_series, 0, composer.args.clone(), Some(composer.args.clone())),
+            (expansion_series(), 0, composer.args.clone(), Some(composer.args.clone()))
+        ]
+    );
+
+    let result = sign_and_verify(&mut context, tx, vec![(&buyer, 0)]);
+    assert_script_error!(result, Payment, InsufficientCapacity);
+}
+
+#[test]
+fn test_success_purchase_with_split_remainder_to_first_recipient() {
+    // 1000 splits into 333 and 666, the shannon left over goes to the composer
+    let cycles = verify_revenue_split_purchase(RevenueSplitPurchase {
+        composer_share: 3333,
+        partner_share: 6667,
+        payment_capacity: 1434,
+        partner_capacity: 666,
+        ..Default::default()
+    })
+    .expect("pass test_success_purchase_with_split_remainder_to_first_recipient");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_purchase_with_split_remainder_dropped() {
+    let result = verify_revenue_split_purchase(RevenueSplitPurchase {
+        composer_share: 3333,
+        partner_share: 6667,
+        payment_capacity: 1433,
+        partner_capacity: 666,
+        ..Default::default()
+    });
+    assert_script_error!(result, Payment, InsufficientCapacity);
+}
+
+fn build_operator_config(price: u64, operator_args: &Bytes) -> Bytes {
+    append_nft_config_extension(
+        build_nft_config(0, price, 5, REVEAL_DELAY, right_nfts()),
+        EXTENSION_OPERATOR,
+        operator_args.to_vec()
+    )
+}
+
+struct OperatorEdit {
+    // builds the new config from the operator args and a stranger's args, so tests can hand the role over
+    new_config: fn(&Bytes, &Bytes) -> Bytes,
+    // capacity of the edited config cell, which is deleted if there is none
+    new_capacity: Option<u64>,
+    signed_by_operator: bool
+}
+
+impl Default for OperatorEdit {
+    fn default() -> Self {
+        OperatorEdit {
+            new_config: |operator, _| build_operator_config(150, operator),
+            new_capacity: Some(1000),
+            signed_by_operator: true
+        }
+    }
+}
+
+fn verify_operator_edit(edit: OperatorEdit) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+    let composer = Wallet::random();
+    let operator = Wallet::random();
+    let stranger = Wallet::random();
+
+    // build partial tx which edits the config cell
+    let outputs = match edit.new_capacity {
+        Some(new_capacity) => vec![(
+            (edit.new_config)(&operator.args, &stranger.args), new_capacity, composer.args.clone(), Some(composer.args.clone())
+        )],
+        None => vec![]
+    };
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(build_operator_config(100, &operator.args), 1000, composer.args.clone(), Some(composer.args.clone()))],
+        outputs,
+        vec![]
+    );
+
+    let signer = if edit.signed_by_operator { &operator } else { &stranger };
+    sign_and_verify(&mut context, tx, vec![(signer, 0)])
+}
+
+#[test]
+fn test_success_update_price_by_operator() {
+    let cycles = verify_operator_edit(OperatorEdit::default())
+        .expect("pass test_success_update_price_by_operator");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_success_pause_sale_by_operator() {
+    let pause_sale = |operator: &Bytes, _: &Bytes| {
+        let sale_window = SaleWindow { start_block: 0, end_block: u64::MAX, paused: true };
+        append_nft_config_extension(build_operator_config(100, operator), EXTENSION_SALE_WINDOW, sale_window.encode())
+    };
+    let cycles = verify_operator_edit(OperatorEdit { new_config: pause_sale, ..Default::default() })
+        .expect("pass test_success_pause_sale_by_operator");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_update_price_by_stranger() {
+    // anyone but the operator is taken for a gift sender, who needs the config of a payment cell in cell deps
+    let result = verify_operator_edit(OperatorEdit { signed_by_operator: false, ..Default::default() });
+    assert_script_error!(result, Payment, MissingCells);
+}
+
+#[test]
+fn test_fail_withdraw_from_config_by_operator() {
+    let result = verify_operator_edit(OperatorEdit { new_capacity: Some(500), ..Default::default() });
+    assert_script_error!(result, Payment, InvalidOperatorOperation);
+}
+
+#[test]
+fn test_fail_delete_config_by_operator() {
+    let result = verify_operator_edit(OperatorEdit { new_capacity: None, ..Default::default() });
+    assert_script_error!(result, Payment, InvalidOperatorOperation);
+}
+
+#[test]
+fn test_fail_change_operator_by_operator() {
+    let result = verify_operator_edit(OperatorEdit {
+        new_config: |_, stranger| build_operator_config(100, stranger),
+        ..Default::default()
+    });
+    assert_script_error!(result, Payment, InvalidOperatorOperation);
+}
+
+#[test]
+fn test_fail_remove_operator_by_operator() {
+    let result = verify_operator_edit(OperatorEdit {
+        new_config: |_, _| build_nft_config(0, 100, 5, REVEAL_DELAY, right_nfts()),
+        ..Default::default()
+    });
+    assert_script_error!(result, Payment, InvalidOperatorOperation);
+}
+
+#[test]
+fn test_fail_redirect_revenue_by_operator() {
+    let redirect_revenue = |operator: &Bytes, stranger: &Bytes| {
+        let stranger_lock_hash = Bytes::from(blake2b_256(stranger).to_vec());
+        append_nft_config_extension(
+            build_operator_config(100, operator),
+            EXTENSION_REVENUE_SPLIT,
+            build_revenue_split(vec![(&stranger_lock_hash, REVENUE_SHARE_TOTAL)])
+        )
+    };
+    let result = verify_operator_edit(OperatorEdit { new_config: redirect_revenue, ..Default::default() });
+    assert_script_error!(result, Payment, InvalidOperatorOperation);
+}
+
+#[test]
+fn test_fail_change_series_by_operator() {
+    let change_series = |operator: &Bytes, _: &Bytes| {
+        append_nft_config_extension(
+            build_nft_config(1, 100, 5, REVEAL_DELAY, right_nfts()),
+            EXTENSION_OPERATOR,
+            operator.to_vec()
+        )
+    };
+    let result = verify_operator_edit(OperatorEdit { new_config: change_series, ..Default::default() });
+    assert_script_error!(result, Payment, InvalidOperatorOperation);
+}
+
+struct PartialReveal {
+    old_count: u32,
+    old_offset: u32,
+    old_anchor: u64,
+    new_count: u32,
+    new_offset: u32,
+    new_anchor: u64,
+    new_capacity: u64,
+    // lottery index the revealed nfts are drawn from
+    draw_from: usize
+}
+
+impl Default for PartialReveal {
+    fn default() -> Self {
+        PartialReveal {
+            old_count: 4,
+            old_offset: 0,
+            old_anchor: 0,
+            new_count: 2,
+            new_offset: 10,
+            new_anchor: PURCHASE_BLOCK,
+            new_capacity: 1100,
+            draw_from: 0
+        }
+    }
+}
+
+// the block of the previous partial reveal, which holds the payment cell once a partial reveal has started
+const PARTIAL_REVEAL_BLOCK: u64 = PURCHASE_BLOCK + REVEAL_DELAY + 5;
+
+fn verify_partial_reveal(reveal: PartialReveal) -> Result<Cycle, Error> {
+    let mut context = Context::default();
+    let composer = Wallet::random();
+    let buyer = Wallet::random();
+
+    // build partial tx, packs were bought in the purchase block which stays the anchor block
+    let config_hash = build_config_hash(&starter_series());
+    let old_data = build_partial_payment_data(reveal.old_count, reveal.old_offset, reveal.old_anchor, config_hash);
+    let new_data = build_partial_payment_data(reveal.new_count, reveal.new_offset, reveal.new_anchor, config_hash);
+    let tx = build_partial_tx(
+        &mut context,
+        vec![(old_data, 1100, composer.args.clone(), Some(buyer.args.clone()))],
+        vec![(new_data, reveal.new_capacity, composer.args.clone(), Some(buyer.args.clone()))],
+        vec![(starter_series(), 0, composer.args.clone(), Some(composer.args.clone()))]
+    );
+
+    // a started partial reveal has moved the payment cell out of the purchase block, whose header
+    // stays in header deps as the anchor
+    let tx = if reveal.old_offset != 0 {
+        let (tx, partial_reveal_header) = append_reveal_header(&mut context, tx, PARTIAL_REVEAL_BLOCK);
+        let payment_out_point = tx.inputs().get(0).unwrap().previous_output();
+        context.link_cell_with_block(payment_out_point, partial_reveal_header.hash(), 0);
+        tx
+    } else {
+        tx
+    };
+
+    // draw the revealed packs starting from the lottery index `draw_from`
+    let (tx, reveal_header) = append_reveal_header(&mut context, tx, PURCHASE_BLOCK + REVEAL_DELAY);
+    let payment_script_hash = tx.output(0).unwrap().type_().to_opt().unwrap().calc_script_hash();
+    let seed = build_lottery_seed(&reveal_header, &payment_script_hash);
+    let reveal_count = reveal.old_count.saturating_sub(reveal.new_count) as usize * 5;
+    let nft_collection = build_nft_collection(right_nfts(), reveal.draw_from + reveal_count, seed).slice(reveal.draw_from * 20..);
+
+    let lock_hash = tx.output(0).unwrap().lock().calc_script_hash();
+    let tx = append_nft_output(&mut context, tx, &buyer, lock_hash.raw_data(), nft_collection);
+    sign_and_verify(&mut context, tx, vec![(&buyer, 0)])
+}
+
+#[test]
+fn test_success_reveal_part_of_nft_packages() {
+    let cycles = verify_partial_reveal(PartialReveal::default())
+        .expect("pass test_success_reveal_part_of_nft_packages");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_success_reveal_rest_of_nft_packages() {
+    let cycles = verify_partial_reveal(PartialReveal {
+        old_count: 2, old_offset: 10, old_anchor: PURCHASE_BLOCK, new_count: 0, new_offset: 0, new_anchor: 0, draw_from: 10,
+        ..Default::default()
+    }).expect("pass test_success_reveal_rest_of_nft_packages");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_success_reveal_next_part_of_nft_packages() {
+    let cycles = verify_partial_reveal(PartialReveal {
+        old_count: 3, old_offset: 5, old_anchor: PURCHASE_BLOCK, new_count: 1, new_offset: 15, draw_from: 5,
+        ..Default::default()
+    }).expect("pass test_success_reveal_next_part_of_nft_packages");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_reveal_rest_of_nft_packages_without_anchor_header() {
+    let result = verify_partial_reveal(PartialReveal {
+        old_count: 2, old_offset: 10, old_anchor: PURCHASE_BLOCK + 1, new_count: 0, new_offset: 0, new_anchor: 0, draw_from: 10,
+        ..Default::default()
+    });
+    assert_script_error!(result, Payment, MissingPaymentHeader);
+}
+
+#[test]
+fn test_fail_reveal_rest_of_nft_packages_from_partial_reveal_block() {
+    let result = verify_partial_reveal(PartialReveal {
+        old_count: 2, old_offset: 10, old_anchor: 0, new_count: 0, new_offset: 0, new_anchor: 0, draw_from: 10,
+        ..Default::default()
+    });
+    assert_script_error!(result, Payment, MissingRevealHeader);
+}
+
+#[test]
+fn test_fail_reroll_revealed_lotteries() {
+    let result = verify_partial_reveal(PartialReveal {
+        old_count: 2, old_offset: 10, old_anchor: PURCHASE_BLOCK, new_count: 0, new_offset: 0, new_anchor: 0, draw_from: 0,
+        ..Default::default()
+    });
+    assert_script_error!(result, Payment, InvalidRevealNFTData);
+}
+
+#[test]
+fn test_fail_partial_reveal_without_advancing_draw_offset() {
+    let result = verify_partial_reveal(PartialReveal { new_offset: 0, ..Default::default() });
+    assert_script_error!(result, Payment, InvalidPaymentData);
+}
+
+#[test]
+fn test_fail_purchase_during_partial_reveal() {
+    let result = verify_partial_reveal(PartialReveal {
+        old_count: 2, old_offset: 10, old_anchor: PURCHASE_BLOCK, new_count: 3, new_offset: 10, new_capacity: 1200,
+        ..Default::default()
+    });
+    assert_script_error!(result, Payment, PartialRevealInProgress);
+}
@@ -3,7 +3,8 @@ use super::{
     *,
 };
 use ckb_system_scripts::BUNDLED_CELL;
-use ckb_testtool::context::Context;
+use error_registry::assert_script_error;
+use ckb_testtool::{builtin::ALWAYS_SUCCESS, context::Context};
 use ckb_tool::ckb_crypto::secp::{Generator, Privkey};
 use ckb_tool::ckb_types::{
     bytes::Bytes,
@@ -20,6 +21,7 @@ fn build_tx(
     context: &mut Context,
     lock_args: Bytes,
     privkey: &Privkey,
+    type_script: Option<Script>,
     input_capacities: Vec<u64>,
     output_capacities: Vec<u64>
 ) -> TransactionView {
@@ -48,6 +50,7 @@ fn build_tx(
                 CellOutput::new_builder()
                     .capacity(cap.pack())
                     .lock(lock_script.clone())
+                    .type_(type_script.clone().pack())
                     .build(),
                 Bytes::from(vec![42]),
             );
@@ -62,6 +65,7 @@ fn build_tx(
             CellOutput::new_builder()
                 .capacity(cap.pack())
                 .lock(lock_script.clone())
+                .type_(type_script.clone().pack())
                 .build()
         })
         .collect::<Vec<CellOutput>>();
@@ -96,7 +100,7 @@ fn test_success_with_match_owner() {
     let lock_args = Bytes::from(hex::decode("58b85c196e5fe80e25b4dab596e7121d219f79fb").unwrap());
     let privkey = Privkey::from(H256(hex::decode("8d929e962f940f75aa32054f19a5ea2ce70ae30bfe4ff7cf2dbed70d556265df").unwrap().try_into().unwrap()));
 
-    let tx = build_tx(&mut context, lock_args, &privkey, vec![1000u64], vec![400u64, 500u64]);
+    let tx = build_tx(&mut context, lock_args, &privkey, None, vec![1000u64], vec![400u64, 500u64]);
 
     // run
     let cycles = context
@@ -114,7 +118,7 @@ fn test_fail_with_mismatch_owner() {
     let lock_args = Bytes::from(hex::decode("58b85c196e5fe80e25b4dab596e7121d219f79fb").unwrap());
     let privkey = keypair.0;
 
-    let tx = build_tx(&mut context, lock_args, &privkey, vec![1000u64], vec![400u64, 500u64]);
+    let tx = build_tx(&mut context, lock_args, &privkey, None, vec![1000u64], vec![400u64, 500u64]);
 
     // run
     let cycles = context
@@ -132,7 +136,7 @@ fn test_success_with_mismatch_owner() {
     let lock_args = Bytes::from(hex::decode("58b85c196e5fe80e25b4dab596e7121d219f79fb").unwrap());
     let privkey = keypair.0;
 
-    let tx = build_tx(&mut context, lock_args, &privkey, vec![1000u64], vec![600u64, 500u64]);
+    let tx = build_tx(&mut context, lock_args, &privkey, None, vec![1000u64], vec![600u64, 500u64]);
 
     // run
     let cycles = context
@@ -140,3 +144,40 @@ fn test_success_with_mismatch_owner() {
         .expect("pass verification");
     println!("consume cycles: {}", cycles);
 }
+
+fn build_delegated_tx(context: &mut Context, privkey: &Privkey, typed: bool) -> TransactionView {
+    // delegate capacity checks to the cells typed by always-success script
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+    let type_script = context
+        .build_script(&always_success_out_point, Bytes::new())
+        .expect("type script");
+    let mut lock_args = hex::decode("58b85c196e5fe80e25b4dab596e7121d219f79fb").unwrap();
+    lock_args.append(&mut type_script.code_hash().raw_data().to_vec());
+
+    let type_script = if typed { Some(type_script) } else { None };
+    build_tx(context, Bytes::from(lock_args), privkey, type_script, vec![1000u64], vec![400u64, 500u64])
+}
+
+#[test]
+fn test_success_with_delegated_capacity() {
+    let mut context = Context::default();
+    let keypair = Generator::random_keypair();
+    let tx = build_delegated_tx(&mut context, &keypair.0, true);
+
+    // run
+    let cycles = context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_fail_with_undelegated_capacity() {
+    let mut context = Context::default();
+    let keypair = Generator::random_keypair();
+    let tx = build_delegated_tx(&mut context, &keypair.0, false);
+
+    // run
+    let result = context.verify_tx(&tx, MAX_CYCLES);
+    assert_script_error!(result, Wallet, CapacityError);
+}